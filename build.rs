@@ -0,0 +1,17 @@
+//! The `embedded-net` feature compiles `nets/default.nnue` into the binary.
+//! When the file is missing, this sets `missing_embedded_net` so the crate
+//! stops with an explanation instead of a bare `include_bytes!` error.
+
+use std::env;
+use std::path::Path;
+
+const EMBEDDED_NET: &str = "nets/default.nnue";
+
+fn main() {
+    println!("cargo::rerun-if-changed={}", EMBEDDED_NET);
+    println!("cargo::rustc-check-cfg=cfg(missing_embedded_net)");
+
+    if env::var_os("CARGO_FEATURE_EMBEDDED_NET").is_some() && !Path::new(EMBEDDED_NET).is_file() {
+        println!("cargo::rustc-cfg=missing_embedded_net");
+    }
+}
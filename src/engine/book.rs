@@ -0,0 +1,230 @@
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use shakmaty::zobrist::Zobrist64;
+use shakmaty::{Chess, EnPassantMode, Move, Position, Role, Square};
+
+pub const DEFAULT_BOOK_DEPTH: u32 = 20;
+pub const MAX_BOOK_DEPTH: u32 = 200;
+
+const ENTRY_SIZE: usize = 16;
+
+/// How to choose among the book moves of a position.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    /// At random, in proportion to the weights.
+    Weighted,
+    /// Always the highest weight.
+    Best,
+}
+
+#[derive(Clone, Copy)]
+struct BookEntry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+}
+
+/// A Polyglot `.bin` opening book: 16-byte big-endian entries of key, move,
+/// weight and learn data, sorted by key.
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn load(path: &str) -> io::Result<Book> {
+        let bytes = fs::read(path)?;
+
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a polyglot book"));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes([entry[8], entry[9]]),
+                weight: u16::from_be_bytes([entry[10], entry[11]]),
+            })
+            .collect();
+
+        // Books are written sorted, but a lookup must not depend on it
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Book { entries })
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The legal book moves of `pos` with their weights.
+    pub fn moves(&self, pos: &Chess) -> Vec<(Move, u16)> {
+        let key = polyglot_key(pos);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| Some((decode_move(pos, entry.mv)?, entry.weight)))
+            .collect()
+    }
+
+    /// A book move for `pos`, `None` when the position is out of book. Moves of
+    /// weight zero are never picked.
+    pub fn pick(&self, pos: &Chess, selection: BookSelection) -> Option<Move> {
+        let moves: Vec<(Move, u16)> = self.moves(pos).into_iter().filter(|&(_, weight)| weight > 0).collect();
+
+        match selection {
+            BookSelection::Best => moves.iter().max_by_key(|&&(_, weight)| weight).map(|&(mv, _)| mv),
+            BookSelection::Weighted => {
+                let total: u64 = moves.iter().map(|&(_, weight)| weight as u64).sum();
+
+                if total == 0 {
+                    return None;
+                }
+
+                let mut target = random() % total;
+
+                for &(mv, weight) in &moves {
+                    if target < weight as u64 {
+                        return Some(mv);
+                    }
+                    target -= weight as u64;
+                }
+
+                None
+            }
+        }
+    }
+}
+
+/// shakmaty's 64-bit Zobrist keys are the Polyglot ones. Polyglot only counts
+/// an en passant square when a pawn stands ready to take, legal or not.
+fn polyglot_key(pos: &Chess) -> u64 {
+    pos.zobrist_hash::<Zobrist64>(EnPassantMode::PseudoLegal).0
+}
+
+/// Polyglot moves pack to, from and promotion into 6 + 6 + 3 bits. Castling
+/// is written as the king taking its own rook, the way shakmaty has it too.
+fn decode_move(pos: &Chess, raw: u16) -> Option<Move> {
+    let to = Square::new((raw & 0x3f) as u32);
+    let from = Square::new(((raw >> 6) & 0x3f) as u32);
+
+    let promotion = match (raw >> 12) & 0x7 {
+        0 => None,
+        1 => Some(Role::Knight),
+        2 => Some(Role::Bishop),
+        3 => Some(Role::Rook),
+        4 => Some(Role::Queen),
+        _ => return None,
+    };
+
+    pos.legal_moves()
+        .into_iter()
+        .find(|mv| mv.from() == Some(from) && mv.to() == to && mv.promotion() == promotion)
+}
+
+/// A fresh xorshift draw, seeded from the clock: book choices only need to
+/// differ between games, not be reproducible.
+fn random() -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+    let mut x = nanos | 1;
+
+    for _ in 0..4 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+    }
+
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::utility::read_position_from_fen;
+    use crate::uci::parser::uci_to_move;
+
+    fn play(moves: &str) -> Chess {
+        let mut pos = Chess::default();
+
+        for mv in moves.split_whitespace() {
+            let mv = uci_to_move(&pos, mv);
+            pos.play_unchecked(mv);
+        }
+
+        pos
+    }
+
+    /// The reference keys from the Polyglot book format description.
+    #[test]
+    fn keys_match_polyglot() {
+        let keys = [
+            ("", 0x463b96181691fc9c),
+            ("e2e4", 0x823c9b50fd114196),
+            ("e2e4 d7d5", 0x0756b94461c50fb0),
+            ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+            ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+        ];
+
+        for (moves, key) in keys {
+            assert_eq!(polyglot_key(&play(moves)), key, "after {:?}", moves);
+        }
+    }
+
+    #[test]
+    fn decodes_castling_and_promotions() {
+        let pos = read_position_from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+
+        let castle = decode_move(&pos, 4 << 6 | 7).unwrap();
+        assert!(matches!(castle, Move::Castle { .. }));
+
+        let promotion = decode_move(&pos, 4 << 12 | 49 << 6 | 57).unwrap();
+        assert_eq!(promotion, uci_to_move(&pos, "b7b8q"));
+
+        assert_eq!(decode_move(&pos, 7 << 12 | 49 << 6 | 57), None);
+        assert_eq!(decode_move(&pos, 12 << 6 | 28), None);
+    }
+
+    #[test]
+    fn picks_the_heaviest_legal_move() {
+        let key = polyglot_key(&Chess::default());
+        let mut bytes = Vec::new();
+
+        // e2e4, d2d4 and g1f3 (weight 0), plus one for an unrelated position
+        let entries = [
+            (key, 12 << 6 | 28, 10u16),
+            (key, 11 << 6 | 27, 30),
+            (key, 6 << 6 | 21, 0),
+            (1, 12 << 6 | 28, 99),
+        ];
+
+        for (key, mv, weight) in entries {
+            bytes.extend_from_slice(&u64::to_be_bytes(key));
+            bytes.extend_from_slice(&u16::to_be_bytes(mv));
+            bytes.extend_from_slice(&u16::to_be_bytes(weight));
+            bytes.extend_from_slice(&[0; 4]);
+        }
+
+        let path = std::env::temp_dir().join(format!("fastpeapea-book-{}.bin", std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        let book = Book::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let book = book.unwrap();
+        let pos = Chess::default();
+
+        assert_eq!(book.entry_count(), 4);
+        assert_eq!(book.moves(&pos).len(), 3);
+        assert_eq!(book.pick(&pos, BookSelection::Best), Some(uci_to_move(&pos, "d2d4")));
+
+        let picked = book.pick(&pos, BookSelection::Weighted).unwrap();
+        assert!(picked == uci_to_move(&pos, "e2e4") || picked == uci_to_move(&pos, "d2d4"));
+    }
+}
@@ -0,0 +1,311 @@
+//! The small TOML subset `Params` files are written in: one `key = value`
+//! per entry, values are numbers or (nested, possibly multi-line) arrays of
+//! numbers, `#` starts a comment. Strings and repeated keys are rejected.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(Debug)]
+pub enum Value {
+    Number(String),
+    Array(Vec<Value>),
+}
+
+/// A `Params` field that can be written to and read from a config file.
+pub trait ConfigValue {
+    /// Arrays of arrays are written one inner array per line.
+    const NESTED: bool = false;
+
+    fn write(&self, out: &mut String);
+    fn read(&mut self, value: &Value) -> Result<(), String>;
+
+    /// Whether the value, or every entry of an array, lies within `min..=max`.
+    /// NaN never does.
+    fn in_range(&self, min: f64, max: f64) -> bool;
+}
+
+macro_rules! number_config_value {
+    ($($ty:ty),*) => {$(
+        impl ConfigValue for $ty {
+            fn write(&self, out: &mut String) {
+                let _ = write!(out, "{:?}", self);
+            }
+
+            fn read(&mut self, value: &Value) -> Result<(), String> {
+                match value {
+                    Value::Number(text) => {
+                        *self = text
+                            .parse()
+                            .map_err(|_| format!("`{}` is not a valid {}", text, stringify!($ty)))?;
+                        Ok(())
+                    }
+                    Value::Array(_) => Err("expected a number, found an array".to_string()),
+                }
+            }
+
+            fn in_range(&self, min: f64, max: f64) -> bool {
+                (min..=max).contains(&(*self as f64))
+            }
+        }
+    )*};
+}
+
+number_config_value!(f32, i32, usize);
+
+impl<T: ConfigValue, const N: usize> ConfigValue for [T; N] {
+    const NESTED: bool = true;
+
+    fn write(&self, out: &mut String) {
+        out.push('[');
+        for (i, item) in self.iter().enumerate() {
+            if T::NESTED {
+                out.push_str("\n    ");
+            } else if i > 0 {
+                out.push_str(", ");
+            }
+            item.write(out);
+            if T::NESTED {
+                out.push(',');
+            }
+        }
+        if T::NESTED {
+            out.push('\n');
+        }
+        out.push(']');
+    }
+
+    fn read(&mut self, value: &Value) -> Result<(), String> {
+        match value {
+            Value::Array(items) if items.len() == N => {
+                for (item, value) in self.iter_mut().zip(items) {
+                    item.read(value)?;
+                }
+                Ok(())
+            }
+            Value::Array(items) => Err(format!("expected {} entries, found {}", N, items.len())),
+            Value::Number(_) => Err(format!("expected an array of {}", N)),
+        }
+    }
+
+    fn in_range(&self, min: f64, max: f64) -> bool {
+        self.iter().all(|item| item.in_range(min, max))
+    }
+}
+
+/// Parses `text` into `(line, key, value)` entries, in file order.
+pub fn parse(text: &str) -> Result<Vec<(usize, String, Value)>, String> {
+    let mut parser = Parser { bytes: text.as_bytes(), pos: 0, line: 1 };
+    let mut entries = Vec::new();
+    let mut keys = HashSet::new();
+
+    loop {
+        parser.skip_blank();
+        if parser.peek().is_none() {
+            return Ok(entries);
+        }
+
+        let line = parser.line;
+        let key = parser.token();
+        if key.is_empty() {
+            return Err(parser.error("expected a key"));
+        }
+
+        parser.skip_blank();
+        if parser.peek() != Some(b'=') {
+            return Err(parser.error("expected `=`"));
+        }
+        parser.pos += 1;
+
+        if !keys.insert(key) {
+            return Err(format!("line {}: duplicate key `{}`", line, key));
+        }
+
+        let value = parser.value()?;
+        entries.push((line, key.to_string(), value));
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    /// Skips whitespace, newlines and comments.
+    fn skip_blank(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\n' => self.line += 1,
+                b'#' => {
+                    while self.peek().is_some_and(|b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                    continue;
+                }
+                _ if byte.is_ascii_whitespace() => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// A key or a number: everything up to the next delimiter.
+    fn token(&mut self) -> &'a str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|b| !b.is_ascii_whitespace() && !b"=[],#".contains(&b))
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or("")
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_blank();
+
+        if matches!(self.peek(), Some(b'"' | b'\'')) {
+            return Err(self.error("quoted values are not supported"));
+        }
+
+        if self.peek() != Some(b'[') {
+            let number = self.token();
+            if number.is_empty() {
+                return Err(self.error("expected a value"));
+            }
+            return Ok(Value::Number(number.to_string()));
+        }
+
+        self.pos += 1;
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                Some(_) => items.push(self.value()?),
+                None => return Err(self.error("unterminated array")),
+            }
+
+            self.skip_blank();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {}
+                Some(_) => return Err(self.error("expected `,` or `]`")),
+                None => return Err(self.error("unterminated array")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::params::Params;
+
+    fn number(value: &Value) -> &str {
+        match value {
+            Value::Number(text) => text,
+            Value::Array(_) => panic!("expected a number"),
+        }
+    }
+
+    fn items(value: &Value) -> &[Value] {
+        match value {
+            Value::Array(items) => items,
+            Value::Number(_) => panic!("expected an array"),
+        }
+    }
+
+    #[test]
+    fn parses_numbers_and_comments() {
+        let entries = parse("# header\na = 1 # trailing\n\n  b=-2.5\n").unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].0, entries[0].1.as_str(), number(&entries[0].2)), (2, "a", "1"));
+        assert_eq!((entries[1].0, entries[1].1.as_str(), number(&entries[1].2)), (4, "b", "-2.5"));
+    }
+
+    #[test]
+    fn parses_nested_multiline_arrays() {
+        let text = "t = [\n    [1, 2], # first\n    [3, 4],\n]\nnext = []\n";
+        let entries = parse(text).unwrap();
+
+        let rows = items(&entries[0].2);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(number(&items(&rows[1])[0]), "3");
+        assert_eq!((entries[1].0, items(&entries[1].2).len()), (5, 0));
+    }
+
+    #[test]
+    fn rejects_quoted_values() {
+        assert_eq!(parse("a = \"1\"").unwrap_err(), "line 1: quoted values are not supported");
+        assert_eq!(parse("a = [1, '2']").unwrap_err(), "line 1: quoted values are not supported");
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        assert_eq!(parse("a = 1\nb = 2\na = 3\n").unwrap_err(), "line 3: duplicate key `a`");
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert_eq!(parse("a = 1\nb 2\n").unwrap_err(), "line 2: expected `=`");
+        assert_eq!(parse("= 2").unwrap_err(), "line 1: expected a key");
+        assert_eq!(parse("a =").unwrap_err(), "line 1: expected a value");
+        assert_eq!(parse("a = [1, 2\n").unwrap_err(), "line 2: unterminated array");
+        assert_eq!(parse("a = [1 2]").unwrap_err(), "line 1: expected `,` or `]`");
+    }
+
+    #[test]
+    fn reads_values_into_fields() {
+        let mut scalar = 0.0f32;
+        let mut array = [0i32; 3];
+        let entries = parse("s = 1.5\nv = [1, -2, 3]\nshort = [1, 2]\nbad = x1").unwrap();
+
+        scalar.read(&entries[0].2).unwrap();
+        array.read(&entries[1].2).unwrap();
+        assert_eq!((scalar, array), (1.5, [1, -2, 3]));
+
+        assert_eq!(array.read(&entries[2].2).unwrap_err(), "expected 3 entries, found 2");
+        assert_eq!(array.read(&entries[0].2).unwrap_err(), "expected an array of 3");
+        assert_eq!(scalar.read(&entries[1].2).unwrap_err(), "expected a number, found an array");
+        assert_eq!(scalar.read(&entries[3].2).unwrap_err(), "`x1` is not a valid f32");
+    }
+
+    #[test]
+    fn params_round_trip() {
+        let mut params = Params::default();
+        params.tempo_bonus = 12.3;
+        params.pst_mg[2][17] = -4.5;
+        params.null_move_reduction = 4;
+
+        let text = params.to_config();
+        let loaded = Params::from_config(&text).unwrap();
+
+        assert_eq!(loaded.to_config(), text);
+        assert_eq!(loaded.tempo_bonus, 12.3);
+        assert_eq!(loaded.pst_mg[2][17], -4.5);
+    }
+
+    #[test]
+    fn params_errors_name_the_line() {
+        let error = Params::from_config("tempo_bonus = 5\nnot_a_param = 1\n").err().unwrap();
+        assert_eq!(error, "line 2: not_a_param: unknown parameter");
+
+        let error = Params::from_config("\nnull_move_depth_divisor = 0\n").err().unwrap();
+        assert_eq!(error, "line 2: null_move_depth_divisor: out of range, expected 2 to 12");
+    }
+}
@@ -1,43 +1,99 @@
 use shakmaty::{Chess, Color, Position, Role};
 use shakmaty::attacks::{bishop_attacks, knight_attacks, rook_attacks};
+use crate::engine::king_safety::king_danger;
 use crate::engine::params::Params;
+use crate::engine::pawns::{evaluate_pawns, PawnTable};
+use crate::engine::types::Score;
+
+// Game phase contribution per piece, P, N, B, R, Q, K. 24 is the full set.
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+const ROLES: [Role; 6] = [
+    Role::Pawn,
+    Role::Knight,
+    Role::Bishop,
+    Role::Rook,
+    Role::Queen,
+    Role::King,
+];
+
+/// Evaluation for the side to move. `pawn_key` is the pawn key of `pos`.
+#[inline(always)]
+pub fn evaluate(pos: &Chess, params: &Params, pawns: &mut PawnTable, pawn_key: u64) -> Score {
+    let score = evaluate_white(pos, params, Some((pawns, pawn_key)));
 
+    if pos.turn() == Color::White {
+        Score::from_cp(score)
+    } else {
+        Score::from_cp(-score)
+    }
+}
 
-
+/// Unrounded evaluation from white's point of view. The pawn cache is optional
+/// because its entries go stale as soon as the pawn weights change.
 #[inline(always)]
-pub fn evaluate(pos: &Chess, params: &Params) -> f32 {
+pub fn evaluate_white(pos: &Chess, params: &Params, pawns: Option<(&mut PawnTable, u64)>) -> f32 {
     let board = pos.board();
-    let mut score = 0.0;
-
-    for &role in &[
-        Role::Pawn,
-        Role::Knight,
-        Role::Bishop,
-        Role::Rook,
-        Role::Queen,
-        Role::King,
-    ] {
+    let mut mg = 0.0;
+    let mut eg = 0.0;
+
+    // === MATERIAL AND PIECE-SQUARE TABLES ===
+    for &role in &ROLES {
         let idx = role as usize - 1;
-        let val = params.piece_values[idx] * params.material_weight;
+        let pst_mg = &params.pst_mg[idx];
+        let pst_eg = &params.pst_eg[idx];
+
+        for sq in board.by_color(Color::White) & board.by_role(role) {
+            let i = sq as usize ^ 56;
+            mg += params.material_mg[idx] * params.material_weight + pst_mg[i] * params.pst_weight;
+            eg += params.material_eg[idx] * params.material_weight + pst_eg[i] * params.pst_weight;
+        }
+
+        for sq in board.by_color(Color::Black) & board.by_role(role) {
+            let i = sq as usize;
+            mg -= params.material_mg[idx] * params.material_weight + pst_mg[i] * params.pst_weight;
+            eg -= params.material_eg[idx] * params.material_weight + pst_eg[i] * params.pst_weight;
+        }
+    }
 
-        let white = board.by_color(Color::White) & board.by_role(role);
-        let black = board.by_color(Color::Black) & board.by_role(role);
+    // === PAWN STRUCTURE ===
+    let (pawns_mg, pawns_eg) = evaluate_pawns(pos, params, pawns);
+    mg += pawns_mg;
+    eg += pawns_eg;
+
+    // === KING SAFETY ===
+    mg -= king_danger(pos, params, Color::White);
+    mg += king_danger(pos, params, Color::Black);
 
-        score += val * white.count() as f32;
-        score -= val * black.count() as f32;
-    }
     // === MOBILITY ===
-    let white_mob = mobility_score(pos, params, Color::White);
-    let black_mob = mobility_score(pos, params,Color::Black);
-    score += (white_mob - black_mob) as f32 ;
-    score +=  add_tempo_bonus(pos, params);
+    let (white_mg, white_eg) = mobility_score(pos, params, Color::White);
+    let (black_mg, black_eg) = mobility_score(pos, params, Color::Black);
+    mg += (white_mg - black_mg) as f32;
+    eg += (white_eg - black_eg) as f32;
 
-    if pos.turn() == Color::White {
-        score
-    } else {
-        -score
-    }
+    let phase = game_phase(pos) as f32;
+    let mut score = (mg * phase + eg * (MAX_PHASE as f32 - phase)) / MAX_PHASE as f32;
+
+    score += add_tempo_bonus(pos, params);
+
+    score
+}
+
+/// Remaining non-pawn material, from `MAX_PHASE` in the opening down to 0 in a
+/// pawn ending. Promotions can push the raw count past the maximum.
+#[inline(always)]
+fn game_phase(pos: &Chess) -> i32 {
+    let board = pos.board();
+
+    let phase: i32 = ROLES
+        .iter()
+        .map(|&role| board.by_role(role).count() as i32 * PHASE_WEIGHTS[role as usize - 1])
+        .sum();
+
+    phase.min(MAX_PHASE)
 }
+
 #[inline(always)]
 fn add_tempo_bonus(pos: &Chess,params: &Params) -> f32{
     if pos.turn() == Color::White {
@@ -47,32 +103,38 @@ fn add_tempo_bonus(pos: &Chess,params: &Params) -> f32{
     }
 }
 #[inline(always)]
-fn mobility_score(pos: &Chess, params: &Params,color: Color) -> i32 {
+fn mobility_score(pos: &Chess, params: &Params,color: Color) -> (i32, i32) {
     let board = pos.board();
     let occ = board.occupied();
     let own = board.by_color(color);
 
-    let mut score = 0;
+    let mut mg = 0;
+    let mut eg = 0;
+
+    let mut add = |role: Role, count: u32| {
+        mg += count as i32 * params.mobility_mg[role as usize - 1];
+        eg += count as i32 * params.mobility_eg[role as usize - 1];
+    };
 
     // === KNIGHTS ===
     let knights = board.by_role(Role::Knight) & own;
     for sq in knights {
         let attacks = knight_attacks(sq) & !own;
-        score += attacks.count() as i32 * params.mobility_bonus[Role::Knight as usize-1];
+        add(Role::Knight, attacks.count() as u32);
     }
 
     // === BISHOPS ===
     let bishops = board.by_role(Role::Bishop) & own;
     for sq in bishops {
         let attacks = bishop_attacks(sq,occ) & !own;
-        score += attacks.count() as i32 * params.mobility_bonus[Role::Bishop as usize-1];
+        add(Role::Bishop, attacks.count() as u32);
     }
     // === ROOKS ===
     let rooks = board.by_role(Role::Rook) & own;
     for sq in rooks {
         let attacks =
             rook_attacks(sq,occ) & !own;
-        score += attacks.count() as i32 * params.mobility_bonus[Role::Rook as usize-1];
+        add(Role::Rook, attacks.count() as u32);
     }
 
     // === QUEENS ===
@@ -80,13 +142,8 @@ fn mobility_score(pos: &Chess, params: &Params,color: Color) -> i32 {
     for sq in queens {
         let attacks =
             (bishop_attacks(sq,occ) | rook_attacks(sq,occ)) & !own;
-        score += attacks.count() as i32 * params.mobility_bonus[Role::Queen as usize-1];
+        add(Role::Queen, attacks.count() as u32);
     }
 
-    score
+    (mg, eg)
 }
-
-
-
-
-
@@ -0,0 +1,163 @@
+use shakmaty::attacks::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks};
+use shakmaty::{Bitboard, Board, Chess, Color, Piece, Position, Role, Square};
+
+use crate::engine::params::Params;
+use crate::engine::pawns::{file_mask, forward_ranks};
+
+// Attack units are looked up in `Params::king_safety_table`
+pub const KING_SAFETY_TABLE_SIZE: usize = 100;
+
+/// Midgame penalty for the danger `color`'s king is in, as a positive number.
+#[inline(always)]
+pub fn king_danger(pos: &Chess, params: &Params, color: Color) -> f32 {
+    let board = pos.board();
+    let Some(king) = board.king_of(color) else {
+        return 0.0;
+    };
+
+    let shelter = pawn_shelter(board, params, color, king);
+    let attack = attack_units(board, params, color, king);
+
+    let units = attack.min(KING_SAFETY_TABLE_SIZE - 1);
+
+    params.king_safety_table[units] * params.king_safety_weight - shelter
+}
+
+/// Shield and storm pawns plus open files on the king's file and its neighbours.
+/// Positive is good for the king.
+fn pawn_shelter(board: &Board, params: &Params, color: Color, king: Square) -> f32 {
+    let own_pawns = board.by_piece(Piece { color, role: Role::Pawn }).0;
+    let their_pawns = board.by_piece(Piece { color: !color, role: Role::Pawn }).0;
+
+    let ahead = forward_ranks(color, king);
+    let center = (king.file() as u32).clamp(1, 6);
+
+    let mut score = 0.0;
+
+    for file in center - 1..=center + 1 {
+        let mask = file_mask(file);
+
+        let shield = nearest_distance(color, king, own_pawns & mask & ahead);
+        score += match shield {
+            Some(1) => params.pawn_shield_mg[1],
+            Some(2) => params.pawn_shield_mg[2],
+            _ => params.pawn_shield_mg[0],
+        };
+
+        if let Some(distance) = nearest_distance(color, king, their_pawns & mask & ahead) {
+            if distance <= 4 {
+                score += params.pawn_storm_mg[distance as usize - 1];
+            }
+        }
+
+        if own_pawns & mask == 0 {
+            score += if their_pawns & mask == 0 {
+                params.king_open_file_mg
+            } else {
+                params.king_semi_open_file_mg
+            };
+        }
+    }
+
+    score
+}
+
+/// Ranks between the king and the closest pawn in `pawns`, all of which are in front of it.
+#[inline(always)]
+fn nearest_distance(color: Color, king: Square, pawns: u64) -> Option<u32> {
+    let pawns = Bitboard(pawns);
+    let nearest = match color {
+        Color::White => pawns.first(),
+        Color::Black => pawns.last(),
+    }?;
+
+    Some(nearest.rank().distance(king.rank()))
+}
+
+/// Attack units against `color`'s king: weighted hits on the king zone by each
+/// attacker, plus safe checks. Counted only with at least two attackers.
+fn attack_units(board: &Board, params: &Params, color: Color, king: Square) -> usize {
+    let them = !color;
+    let occupied = board.occupied();
+    let enemy = board.by_color(them);
+
+    let ring = king_attacks(king) | Bitboard::from(king);
+    let zone = ring | forward_shift(color, ring);
+
+    let defended = attacked_by(board, color);
+
+    let rook_checks = rook_attacks(king, occupied);
+    let bishop_checks = bishop_attacks(king, occupied);
+    let knight_checks = knight_attacks(king);
+
+    let mut attackers = 0;
+    let mut units = 0;
+
+    for role in [Role::Knight, Role::Bishop, Role::Rook, Role::Queen] {
+        let idx = role as usize - 1;
+        let checks = match role {
+            Role::Knight => knight_checks,
+            Role::Bishop => bishop_checks,
+            Role::Rook => rook_checks,
+            _ => bishop_checks | rook_checks,
+        };
+
+        for sq in board.by_piece(Piece { color: them, role }) {
+            let attacks = piece_attacks(role, sq, occupied);
+
+            let hits = (attacks & zone).count();
+            if hits > 0 {
+                attackers += 1;
+                units += hits * params.king_attack_weight[idx] as usize;
+            }
+
+            let safe_checks = attacks & checks & !enemy & !defended;
+            units += safe_checks.count() * params.safe_check_weight[idx] as usize;
+        }
+    }
+
+    if attackers < 2 {
+        return 0;
+    }
+
+    units
+}
+
+#[inline(always)]
+fn piece_attacks(role: Role, sq: Square, occupied: Bitboard) -> Bitboard {
+    match role {
+        Role::Knight => knight_attacks(sq),
+        Role::Bishop => bishop_attacks(sq, occupied),
+        Role::Rook => rook_attacks(sq, occupied),
+        Role::Queen => bishop_attacks(sq, occupied) | rook_attacks(sq, occupied),
+        Role::King => king_attacks(sq),
+        Role::Pawn => Bitboard::EMPTY,
+    }
+}
+
+/// Every square `color` attacks with any piece.
+fn attacked_by(board: &Board, color: Color) -> Bitboard {
+    let occupied = board.occupied();
+    let mut attacked = Bitboard::EMPTY;
+
+    for sq in board.by_piece(Piece { color, role: Role::Pawn }) {
+        attacked |= pawn_attacks(color, sq);
+    }
+
+    for role in [Role::Knight, Role::Bishop, Role::Rook, Role::Queen, Role::King] {
+        for sq in board.by_piece(Piece { color, role }) {
+            attacked |= piece_attacks(role, sq, occupied);
+        }
+    }
+
+    attacked
+}
+
+/// `squares` moved one rank towards the opponent.
+#[inline(always)]
+fn forward_shift(color: Color, squares: Bitboard) -> Bitboard {
+    match color {
+        Color::White => Bitboard(squares.0 << 8),
+        Color::Black => Bitboard(squares.0 >> 8),
+    }
+}
@@ -0,0 +1,334 @@
+use std::sync::Arc;
+
+use shakmaty::zobrist::{Zobrist64, ZobristValue};
+use shakmaty::{CastlingSide, Chess, Color, EnPassantMode, Move, Position, Role, Square};
+
+use crate::engine::nnue::{Network, NnueState};
+use crate::engine::pawns::pawn_key;
+use crate::engine::see;
+use crate::engine::types::MAX_PLY;
+
+/// What search tracks alongside the position at one ply of the current line.
+#[derive(Clone, Copy, Default)]
+struct Frame {
+    hash: u64,
+    pawn_key: u64,
+    /// Knights, bishops, rooks and queens per color, in `SEE_VALUES`.
+    non_pawn_material: [i32; 2],
+    /// The move that led here, `None` at the root and after a null move.
+    last_move: Option<Move>,
+    /// Repetition floor to restore when this frame is unmade.
+    parent_floor: usize,
+}
+
+/// Search-side board: the single way search walks the tree. Making a move keeps
+/// the hash, pawn key, material, NNUE accumulators and repetition history of the
+/// child in step; unmaking steps back to the parent's.
+///
+/// shakmaty has no undo, so the position itself is written into a per-ply slot
+/// the caller owns, and the parent stays in its own slot above it. Everything
+/// else lives in per-ply frames and is restored by moving the ply back.
+pub struct SearchBoard {
+    frames: Box<[Frame]>,
+    ply: usize,
+    /// Hashes of the game so far followed by the current line, for repetitions.
+    history: Vec<u64>,
+    /// Start of the part of `history` a repetition may be found in. Nothing
+    /// before a null move can be repeated by a real game line.
+    repetition_floor: usize,
+    /// NNUE accumulators when a net is loaded, otherwise the handcrafted eval is used.
+    nnue: Option<NnueState>,
+}
+
+impl SearchBoard {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![Frame::default(); MAX_PLY + 1].into_boxed_slice(),
+            ply: 0,
+            history: Vec::with_capacity(256 + MAX_PLY),
+            repetition_floor: 0,
+            nnue: None,
+        }
+    }
+
+    /// Hashes of the positions played before the root, the root itself last.
+    pub fn set_history(&mut self, history: Vec<u64>) {
+        self.history = history;
+    }
+
+    pub fn history(&self) -> &[u64] {
+        &self.history
+    }
+
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(NnueState::new);
+    }
+
+    pub fn network(&self) -> Option<&Arc<Network>> {
+        self.nnue.as_ref().map(NnueState::network)
+    }
+
+    /// Starts a search from `pos`, recomputing everything from scratch.
+    pub fn set_root(&mut self, pos: &Chess) {
+        let board = pos.board();
+        let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+        let mut non_pawn_material = [0; 2];
+
+        for sq in board.occupied() & !board.pawns() {
+            let piece = board.piece_at(sq).expect("occupied square");
+            non_pawn_material[piece.color as usize] += see::value(piece.role);
+        }
+
+        self.ply = 0;
+        self.repetition_floor = 0;
+        self.frames[0] = Frame {
+            hash,
+            pawn_key: pawn_key(board),
+            non_pawn_material,
+            last_move: None,
+            parent_floor: 0,
+        };
+
+        if self.history.last() != Some(&hash) {
+            self.history.push(hash);
+        }
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.refresh(0, pos);
+        }
+    }
+
+    /// Plays `mv` in `pos`, the position at the current ply, writing the result
+    /// into `child`, the slot for the next ply.
+    #[inline(always)]
+    pub fn make_move(&mut self, pos: &Chess, mv: Move, child: &mut Chess) {
+        child.clone_from(pos);
+        child.play_unchecked(mv);
+
+        let parent = self.frames[self.ply];
+        let us = pos.turn();
+        let mut frame = Frame {
+            hash: hash_after_move(pos, parent.hash, mv, child),
+            last_move: Some(mv),
+            parent_floor: self.repetition_floor,
+            ..parent
+        };
+
+        match mv {
+            Move::Normal { role, from, capture, to, promotion } => {
+                if role == Role::Pawn {
+                    frame.pawn_key ^= pawn_square_key(from, us);
+
+                    match promotion {
+                        Some(promoted) => frame.non_pawn_material[us as usize] += see::value(promoted),
+                        None => frame.pawn_key ^= pawn_square_key(to, us),
+                    }
+                }
+
+                match capture {
+                    Some(Role::Pawn) => frame.pawn_key ^= pawn_square_key(to, !us),
+                    Some(captured) => frame.non_pawn_material[!us as usize] -= see::value(captured),
+                    None => {}
+                }
+            }
+            Move::EnPassant { from, to } => {
+                frame.pawn_key ^= pawn_square_key(from, us);
+                frame.pawn_key ^= pawn_square_key(to, us);
+                frame.pawn_key ^= pawn_square_key(Square::from_coords(to.file(), from.rank()), !us);
+            }
+            Move::Castle { .. } => {}
+            Move::Put { .. } => frame.pawn_key = pawn_key(child.board()),
+        }
+
+        debug_assert_eq!(frame.pawn_key, pawn_key(child.board()), "incremental pawn key diverged after {:?}", mv);
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.make_move(self.ply, pos, &mv);
+        }
+
+        self.ply += 1;
+        self.frames[self.ply] = frame;
+        self.history.push(frame.hash);
+    }
+
+    #[inline(always)]
+    pub fn unmake_move(&mut self) {
+        self.repetition_floor = self.frames[self.ply].parent_floor;
+        self.history.pop();
+        self.ply -= 1;
+    }
+
+    /// Passes the turn in `pos`, writing the result into `child`. `false` if that
+    /// is not a legal position, i.e. in check, in which case nothing is made.
+    #[inline(always)]
+    pub fn make_null_move(&mut self, pos: &Chess, child: &mut Chess) -> bool {
+        let Ok(null) = pos.clone().swap_turn() else {
+            return false;
+        };
+        *child = null;
+
+        let parent = self.frames[self.ply];
+
+        if let Some(nnue) = &mut self.nnue {
+            nnue.make_null_move(self.ply);
+        }
+
+        self.ply += 1;
+        self.frames[self.ply] = Frame {
+            hash: hash_after_null_move(pos, parent.hash),
+            last_move: None,
+            parent_floor: self.repetition_floor,
+            ..parent
+        };
+        self.history.push(self.frames[self.ply].hash);
+        self.repetition_floor = self.history.len() - 1;
+
+        true
+    }
+
+    #[inline(always)]
+    pub fn unmake_null_move(&mut self) {
+        self.unmake_move();
+    }
+
+    #[inline(always)]
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    #[inline(always)]
+    pub fn hash(&self) -> u64 {
+        self.frames[self.ply].hash
+    }
+
+    #[inline(always)]
+    pub fn pawn_key(&self) -> u64 {
+        self.frames[self.ply].pawn_key
+    }
+
+    #[inline(always)]
+    pub fn non_pawn_material(&self, color: Color) -> i32 {
+        self.frames[self.ply].non_pawn_material[color as usize]
+    }
+
+    #[inline(always)]
+    pub fn last_move(&self) -> Option<Move> {
+        self.frames[self.ply].last_move
+    }
+
+    #[inline(always)]
+    pub fn nnue(&self) -> Option<&NnueState> {
+        self.nnue.as_ref()
+    }
+
+    /// Whether the current position, `pos`, already occurred twice since the
+    /// last irreversible move.
+    #[inline(always)]
+    pub fn is_threefold(&self, pos: &Chess) -> bool {
+        let len = self.history.len();
+
+        let Some(&current) = self.history.last() else {
+            return false;
+        };
+
+        let start = len
+            .saturating_sub(pos.halfmoves() as usize + 1)
+            .max(self.repetition_floor);
+
+        self.history[start..len - 1]
+            .iter()
+            .filter(|&&hash| hash == current)
+            .nth(1)
+            .is_some()
+    }
+}
+
+#[inline(always)]
+fn pawn_square_key(sq: Square, color: Color) -> u64 {
+    Zobrist64::zobrist_for_piece(sq, Role::Pawn.of(color)).0
+}
+
+/// Zobrist hash of `child`, the position after `mv` in `pos`, from the hash of
+/// `pos`. Matches `zobrist_hash::<Zobrist64>(EnPassantMode::Legal)`, which is
+/// checked in debug builds.
+#[inline(always)]
+pub fn hash_after_move(pos: &Chess, hash: u64, mv: Move, child: &Chess) -> u64 {
+    let key = update_hash(pos, hash, mv, child)
+        .unwrap_or_else(|| child.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0);
+
+    debug_assert_eq!(
+        key,
+        child.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0,
+        "incremental hash diverged after {:?}",
+        mv
+    );
+
+    key
+}
+
+/// The incremental update alone, unchecked: `None` for drops, which have no
+/// update of their own. Perft checks this against a full recompute.
+#[inline(always)]
+pub fn update_hash(pos: &Chess, hash: u64, mv: Move, child: &Chess) -> Option<u64> {
+    let us = pos.turn();
+    let mut key = Zobrist64(hash) ^ Zobrist64::zobrist_for_white_turn();
+
+    match mv {
+        Move::Normal { role, from, capture, to, promotion } => {
+            key ^= Zobrist64::zobrist_for_piece(from, role.of(us));
+            key ^= Zobrist64::zobrist_for_piece(to, promotion.unwrap_or(role).of(us));
+
+            if let Some(captured) = capture {
+                key ^= Zobrist64::zobrist_for_piece(to, captured.of(!us));
+            }
+        }
+        Move::EnPassant { from, to } => {
+            key ^= Zobrist64::zobrist_for_piece(from, Role::Pawn.of(us));
+            key ^= Zobrist64::zobrist_for_piece(to, Role::Pawn.of(us));
+            key ^= Zobrist64::zobrist_for_piece(Square::from_coords(to.file(), from.rank()), Role::Pawn.of(!us));
+        }
+        Move::Castle { king, rook } => {
+            let side = mv.castling_side().expect("castling move");
+            key ^= Zobrist64::zobrist_for_piece(king, Role::King.of(us));
+            key ^= Zobrist64::zobrist_for_piece(rook, Role::Rook.of(us));
+            key ^= Zobrist64::zobrist_for_piece(side.king_to(us), Role::King.of(us));
+            key ^= Zobrist64::zobrist_for_piece(side.rook_to(us), Role::Rook.of(us));
+        }
+        Move::Put { .. } => return None,
+    }
+
+    if pos.castles().castling_rights() != child.castles().castling_rights() {
+        key ^= castling_key(pos) ^ castling_key(child);
+    }
+
+    key ^= en_passant_key(pos) ^ en_passant_key(child);
+
+    Some(key.0)
+}
+
+/// Hash after the side to move passes. `swap_turn` drops the en passant square.
+#[inline(always)]
+pub fn hash_after_null_move(pos: &Chess, hash: u64) -> u64 {
+    (Zobrist64(hash) ^ Zobrist64::zobrist_for_white_turn() ^ en_passant_key(pos)).0
+}
+
+#[inline(always)]
+fn castling_key(pos: &Chess) -> Zobrist64 {
+    let mut key = Zobrist64::default();
+
+    for color in Color::ALL {
+        for side in CastlingSide::ALL {
+            if pos.castles().has(color, side) {
+                key ^= Zobrist64::zobrist_for_castling_right(color, side);
+            }
+        }
+    }
+
+    key
+}
+
+#[inline(always)]
+fn en_passant_key(pos: &Chess) -> Zobrist64 {
+    pos.ep_square(EnPassantMode::Legal)
+        .map_or(Zobrist64::default(), |sq| Zobrist64::zobrist_for_en_passant_file(sq.file()))
+}
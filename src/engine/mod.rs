@@ -1,9 +1,17 @@
 pub mod params;
+pub(crate) mod config;
 pub mod eval;
+pub(crate) mod pawns;
+pub(crate) mod king_safety;
+pub(crate) mod nnue;
 pub mod search;
 pub(crate) mod utility;
 pub(crate) mod time_manager;
-mod types;
+pub(crate) mod types;
 pub(crate) mod state;
-mod tt;
-mod make_move;
+pub(crate) mod tt;
+pub(crate) mod see;
+pub(crate) mod perft;
+pub(crate) mod make_move;
+pub(crate) mod tablebase;
+pub(crate) mod book;
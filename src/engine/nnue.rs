@@ -0,0 +1,254 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use shakmaty::{Chess, Color, Move, Piece, Position, Role, Square};
+
+use crate::engine::types::{Score, MAX_PLY};
+
+pub const HIDDEN_SIZE: usize = 256;
+pub const EMBEDDED_NET_NAME: &str = "<embedded>";
+pub const HAS_EMBEDDED_NET: bool = cfg!(feature = "embedded-net");
+
+const INPUT_SIZE: usize = 768;
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+// Keeps a badly trained net from producing scores in the mate range
+const MAX_EVAL: i32 = 20000;
+
+#[cfg(all(feature = "embedded-net", not(missing_embedded_net)))]
+static EMBEDDED_NET: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/nets/default.nnue"));
+
+// Set by build.rs when there is no net to embed
+#[cfg(all(feature = "embedded-net", missing_embedded_net))]
+static EMBEDDED_NET: &[u8] = compile_error!(
+    "the `embedded-net` feature embeds `nets/default.nnue`, which does not exist: \
+     add a net there (see the README) or build without the feature"
+);
+
+/// One perspective's hidden layer, aligned so the update loops vectorise.
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    values: [i16; HIDDEN_SIZE],
+}
+
+impl Accumulator {
+    const ZERO: Accumulator = Accumulator { values: [0; HIDDEN_SIZE] };
+
+    #[inline(always)]
+    fn add(&mut self, weights: &Accumulator) {
+        for (value, &weight) in self.values.iter_mut().zip(&weights.values) {
+            *value = value.wrapping_add(weight);
+        }
+    }
+
+    #[inline(always)]
+    fn sub(&mut self, weights: &Accumulator) {
+        for (value, &weight) in self.values.iter_mut().zip(&weights.values) {
+            *value = value.wrapping_sub(weight);
+        }
+    }
+}
+
+/// A (768 -> HIDDEN_SIZE) x 2 -> 1 perspective network with a SCReLU hidden layer.
+///
+/// Nets are read in the raw quantised layout bullet writes, all little-endian
+/// `i16`: feature weights `[768][HIDDEN_SIZE]`, feature biases `[HIDDEN_SIZE]`,
+/// output weights `[2 * HIDDEN_SIZE]` (side to move first) and the output bias,
+/// padded with zeroes to a multiple of 64 bytes.
+pub struct Network {
+    feature_weights: Box<[Accumulator]>,
+    feature_bias: Accumulator,
+    output_weights: [Accumulator; 2],
+    output_bias: i16,
+}
+
+impl Network {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Network> {
+        Network::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Network> {
+        let expected = 2 * (INPUT_SIZE * HIDDEN_SIZE + HIDDEN_SIZE + 2 * HIDDEN_SIZE + 1);
+
+        if bytes.len() < expected || bytes.len() - expected >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a {}-byte net with {} hidden neurons, got {} bytes", expected, HIDDEN_SIZE, bytes.len()),
+            ));
+        }
+
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+
+        let mut read_accumulator = || {
+            let mut acc = Accumulator::ZERO;
+            for value in acc.values.iter_mut() {
+                *value = values.next().unwrap_or(0);
+            }
+            acc
+        };
+
+        let feature_weights = (0..INPUT_SIZE).map(|_| read_accumulator()).collect();
+        let feature_bias = read_accumulator();
+        let output_weights = [read_accumulator(), read_accumulator()];
+        let output_bias = values.next().unwrap_or(0);
+
+        Ok(Network {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// The net compiled into the binary, if it was built with one. The
+    /// `embedded-net` feature bakes in `nets/default.nnue`.
+    pub fn embedded() -> Option<Arc<Network>> {
+        #[cfg(feature = "embedded-net")]
+        {
+            Some(Arc::new(Network::from_bytes(EMBEDDED_NET).expect("embedded net is malformed")))
+        }
+
+        #[cfg(not(feature = "embedded-net"))]
+        {
+            None
+        }
+    }
+
+    /// Output of the network for the side to move, from its two accumulators.
+    #[inline(always)]
+    fn forward(&self, us: &Accumulator, them: &Accumulator) -> i32 {
+        let mut sum = 0i32;
+
+        for (acc, weights) in [us, them].into_iter().zip(&self.output_weights) {
+            for (&value, &weight) in acc.values.iter().zip(&weights.values) {
+                let clipped = (value as i32).clamp(0, QA);
+                sum += clipped * weight as i32 * clipped;
+            }
+        }
+
+        (sum / QA + self.output_bias as i32) * SCALE / (QA * QB)
+    }
+}
+
+/// Per-thread accumulator stack, one pair (white, black perspective) per ply.
+///
+/// Search copies positions instead of unmaking moves, so making a move fills
+/// `ply + 1` from `ply` and unmaking is simply going back to `ply`.
+pub struct NnueState {
+    network: Arc<Network>,
+    stack: Box<[[Accumulator; 2]]>,
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>) -> Self {
+        Self {
+            network,
+            stack: vec![[Accumulator::ZERO; 2]; MAX_PLY + 1].into_boxed_slice(),
+        }
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Recomputes the accumulators at `ply` from scratch.
+    pub fn refresh(&mut self, ply: usize, pos: &Chess) {
+        let board = pos.board();
+
+        for perspective in [Color::White, Color::Black] {
+            let mut acc = self.network.feature_bias;
+
+            for sq in board.occupied() {
+                let piece = board.piece_at(sq).expect("occupied square");
+                acc.add(&self.network.feature_weights[feature_index(perspective, piece, sq)]);
+            }
+
+            self.stack[ply][perspective as usize] = acc;
+        }
+    }
+
+    /// Fills the accumulators at `ply + 1` for `mv` played in `pos` (the position before the move).
+    #[inline(always)]
+    pub fn make_move(&mut self, ply: usize, pos: &Chess, mv: &Move) {
+        let us = pos.turn();
+        let mut added: [Option<(Piece, Square)>; 2] = [None; 2];
+        let mut removed: [Option<(Piece, Square)>; 2] = [None; 2];
+
+        match *mv {
+            Move::Normal { role, from, capture, to, promotion } => {
+                removed[0] = Some((role.of(us), from));
+                added[0] = Some((promotion.unwrap_or(role).of(us), to));
+                removed[1] = capture.map(|captured| (captured.of(!us), to));
+            }
+            Move::EnPassant { from, to } => {
+                removed[0] = Some((Role::Pawn.of(us), from));
+                added[0] = Some((Role::Pawn.of(us), to));
+                removed[1] = Some((Role::Pawn.of(!us), Square::from_coords(to.file(), from.rank())));
+            }
+            Move::Castle { king, rook } => {
+                let side = mv.castling_side().expect("castling move");
+                removed[0] = Some((Role::King.of(us), king));
+                removed[1] = Some((Role::Rook.of(us), rook));
+                added[0] = Some((Role::King.of(us), side.king_to(us)));
+                added[1] = Some((Role::Rook.of(us), side.rook_to(us)));
+            }
+            Move::Put { .. } => {
+                let mut child = pos.clone();
+                child.play_unchecked(*mv);
+                self.refresh(ply + 1, &child);
+                return;
+            }
+        }
+
+        let (parents, children) = self.stack.split_at_mut(ply + 1);
+        let (parent, child) = (&parents[ply], &mut children[0]);
+        let weights = &self.network.feature_weights;
+
+        for perspective in [Color::White, Color::Black] {
+            let acc = &mut child[perspective as usize];
+            *acc = parent[perspective as usize];
+
+            for &(piece, sq) in removed.iter().flatten() {
+                acc.sub(&weights[feature_index(perspective, piece, sq)]);
+            }
+            for &(piece, sq) in added.iter().flatten() {
+                acc.add(&weights[feature_index(perspective, piece, sq)]);
+            }
+        }
+    }
+
+    /// A null move changes no pieces, only whose perspective comes first.
+    #[inline(always)]
+    pub fn make_null_move(&mut self, ply: usize) {
+        self.stack[ply + 1] = self.stack[ply];
+    }
+
+    #[inline(always)]
+    pub fn evaluate(&self, ply: usize, turn: Color) -> Score {
+        let accumulators = &self.stack[ply];
+        let us = &accumulators[turn as usize];
+        let them = &accumulators[!turn as usize];
+
+        Score(self.network.forward(us, them).clamp(-MAX_EVAL, MAX_EVAL))
+    }
+}
+
+/// Input index of `piece` on `sq` seen from `perspective`: own pieces first,
+/// and the board flipped for black so both sides share weights.
+#[inline(always)]
+fn feature_index(perspective: Color, piece: Piece, sq: Square) -> usize {
+    let side = (piece.color != perspective) as usize;
+    let sq = match perspective {
+        Color::White => sq as usize,
+        Color::Black => sq as usize ^ 56,
+    };
+
+    side * 384 + (piece.role as usize - 1) * 64 + sq
+}
@@ -1,23 +1,690 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::engine::config::{self, ConfigValue};
+use crate::engine::king_safety::KING_SAFETY_TABLE_SIZE;
 
 #[derive(Clone)]
 pub struct Params {
-    pub piece_values: [f32; 6],
-    pub _pst: [[f32; 64]; 6],
+    pub material_mg: [f32; 6],
+    pub material_eg: [f32; 6],
     pub material_weight: f32,
-    pub _pst_weight: f32,
-    pub mobility_bonus: [i32; 6],
-    pub tempo_bonus: f32
+    pub pst_mg: [[f32; 64]; 6],
+    pub pst_eg: [[f32; 64]; 6],
+    pub pst_weight: f32,
+    pub mobility_mg: [i32; 6],
+    pub mobility_eg: [i32; 6],
+    pub doubled_pawn_mg: f32,
+    pub doubled_pawn_eg: f32,
+    pub isolated_pawn_mg: f32,
+    pub isolated_pawn_eg: f32,
+    pub backward_pawn_mg: f32,
+    pub backward_pawn_eg: f32,
+    pub connected_pawn_mg: f32, // per rank
+    pub connected_pawn_eg: f32,
+    pub passed_pawn_mg: [f32; 8], // by relative rank
+    pub passed_pawn_eg: [f32; 8],
+    pub passed_king_distance_eg: f32,
+    pub passed_free_path_mg: f32,
+    pub passed_free_path_eg: f32,
+    pub pawn_shield_mg: [f32; 3], // no shield pawn, one rank ahead, two ranks ahead
+    pub pawn_storm_mg: [f32; 4], // enemy pawn one to four ranks ahead
+    pub king_semi_open_file_mg: f32,
+    pub king_open_file_mg: f32,
+    pub king_attack_weight: [i32; 6], // attack units per king zone square hit
+    pub safe_check_weight: [i32; 6], // attack units per safe checking square
+    pub king_safety_table: [f32; KING_SAFETY_TABLE_SIZE],
+    pub king_safety_weight: f32,
+    pub tempo_bonus: f32,
+    pub aspiration_window: i32,
+    pub null_move_reduction: usize,
+    pub null_move_depth_divisor: usize,
+    pub null_move_eval_divisor: i32,
+    pub null_move_verify_depth: usize,
+    pub lmr_base: f32,
+    pub lmr_divisor: f32,
+    pub see_quiet_margin: i32,
 }
 
 impl Params {
     pub fn default() -> Self {
         Self {
-            piece_values: [100.0, 320.0, 330.0, 500.0, 900.0, 0.0], // P, N, B, R, Q, K
-            _pst: [[0.0; 64]; 6], // initialize later or fill with tuning values
+            material_mg: [82.0, 337.0, 365.0, 477.0, 1025.0, 0.0], // P, N, B, R, Q, K
+            material_eg: [94.0, 281.0, 297.0, 512.0, 936.0, 0.0],
             material_weight: 1.0,
-            _pst_weight: 1.0,
-            mobility_bonus:  [0, 3, 2, 2, 0, 0],
-            tempo_bonus: 10.0
+            pst_mg: pst_to_f32(&PST_MG),
+            pst_eg: pst_to_f32(&PST_EG),
+            pst_weight: 1.0,
+            mobility_mg: [0, 3, 2, 2, 1, 0],
+            mobility_eg: [0, 3, 3, 4, 2, 0],
+            doubled_pawn_mg: -10.0,
+            doubled_pawn_eg: -20.0,
+            isolated_pawn_mg: -10.0,
+            isolated_pawn_eg: -15.0,
+            backward_pawn_mg: -8.0,
+            backward_pawn_eg: -10.0,
+            connected_pawn_mg: 3.0,
+            connected_pawn_eg: 2.0,
+            passed_pawn_mg: [0.0, 0.0, 0.0, 5.0, 10.0, 20.0, 30.0, 0.0],
+            passed_pawn_eg: [0.0, 5.0, 5.0, 10.0, 20.0, 35.0, 50.0, 0.0],
+            passed_king_distance_eg: 5.0,
+            passed_free_path_mg: 5.0,
+            passed_free_path_eg: 20.0,
+            pawn_shield_mg: [-20.0, 12.0, 6.0],
+            pawn_storm_mg: [-5.0, -25.0, -15.0, -5.0],
+            king_semi_open_file_mg: -15.0,
+            king_open_file_mg: -25.0,
+            king_attack_weight: [0, 2, 2, 3, 5, 0],
+            safe_check_weight: [0, 4, 2, 4, 4, 0],
+            king_safety_table: KING_SAFETY_TABLE.map(|v| v as f32),
+            king_safety_weight: 1.0,
+            tempo_bonus: 10.0,
+            aspiration_window: 25,
+            null_move_reduction: 3,
+            null_move_depth_divisor: 6,
+            null_move_eval_divisor: 200,
+            null_move_verify_depth: 10,
+            lmr_base: 0.75,
+            lmr_divisor: 2.25,
+            see_quiet_margin: 20,
+        }
+    }
+}
+
+/// A weight, or a (nested) array of them, that the tuner can address as a flat
+/// list of numbers. Integer weights are tuned as reals and rounded back.
+pub trait Weights {
+    fn weight_count(&self) -> usize;
+    fn flatten(&self, out: &mut Vec<f32>);
+    fn unflatten(&mut self, values: &mut dyn Iterator<Item = f32>);
+    fn get_mut(&mut self, index: usize) -> WeightMut<'_>;
+}
+
+pub enum WeightMut<'a> {
+    F32(&'a mut f32),
+    I32(&'a mut i32),
+}
+
+impl WeightMut<'_> {
+    pub fn set(&mut self, value: f32) {
+        match self {
+            WeightMut::F32(w) => **w = value,
+            WeightMut::I32(w) => **w = value.round() as i32,
+        }
+    }
+}
+
+impl Weights for f32 {
+    fn weight_count(&self) -> usize {
+        1
+    }
+
+    fn flatten(&self, out: &mut Vec<f32>) {
+        out.push(*self);
+    }
+
+    fn unflatten(&mut self, values: &mut dyn Iterator<Item = f32>) {
+        if let Some(value) = values.next() {
+            *self = value;
+        }
+    }
+
+    fn get_mut(&mut self, _index: usize) -> WeightMut<'_> {
+        WeightMut::F32(self)
+    }
+}
+
+impl Weights for i32 {
+    fn weight_count(&self) -> usize {
+        1
+    }
+
+    fn flatten(&self, out: &mut Vec<f32>) {
+        out.push(*self as f32);
+    }
+
+    fn unflatten(&mut self, values: &mut dyn Iterator<Item = f32>) {
+        if let Some(value) = values.next() {
+            *self = value.round() as i32;
+        }
+    }
+
+    fn get_mut(&mut self, _index: usize) -> WeightMut<'_> {
+        WeightMut::I32(self)
+    }
+}
+
+impl<T: Weights, const N: usize> Weights for [T; N] {
+    fn weight_count(&self) -> usize {
+        self.iter().map(Weights::weight_count).sum()
+    }
+
+    fn flatten(&self, out: &mut Vec<f32>) {
+        for item in self {
+            item.flatten(out);
+        }
+    }
+
+    fn unflatten(&mut self, values: &mut dyn Iterator<Item = f32>) {
+        for item in self {
+            item.unflatten(values);
+        }
+    }
+
+    fn get_mut(&mut self, index: usize) -> WeightMut<'_> {
+        let len = self[0].weight_count();
+        self[index / len].get_mut(index % len)
+    }
+}
+
+/// Declares which `Params` fields the tuner optimises, in flattening order.
+/// Scale factors, search parameters and weights that only move a table index
+/// (attack weights) are left out: they are not linear in the evaluation.
+macro_rules! tunable {
+    ($($field:ident),* $(,)?) => {
+        impl Params {
+            pub fn tunable_len(&self) -> usize {
+                0 $(+ self.$field.weight_count())*
+            }
+
+            pub fn tunable_weights(&self) -> Vec<f32> {
+                let mut out = Vec::with_capacity(self.tunable_len());
+                $(self.$field.flatten(&mut out);)*
+                out
+            }
+
+            pub fn set_tunable_weights(&mut self, values: &[f32]) {
+                let mut values = values.iter().copied();
+                $(self.$field.unflatten(&mut values);)*
+            }
+
+            #[allow(unused_assignments)]
+            pub fn tunable_weight_mut(&mut self, mut index: usize) -> WeightMut<'_> {
+                $(
+                    let len = self.$field.weight_count();
+                    if index < len {
+                        return self.$field.get_mut(index);
+                    }
+                    index -= len;
+                )*
+                panic!("tunable weight index out of range");
+            }
+
+            /// The tunable fields as struct literal lines for `Params::default`.
+            pub fn tunable_source(&self) -> String {
+                let mut out = String::new();
+                $(out.push_str(&format!("{}: {:?},\n", stringify!($field), self.$field));)*
+                out
+            }
+        }
+    };
+}
+
+tunable!(
+    material_mg,
+    material_eg,
+    pst_mg,
+    pst_eg,
+    mobility_mg,
+    mobility_eg,
+    doubled_pawn_mg,
+    doubled_pawn_eg,
+    isolated_pawn_mg,
+    isolated_pawn_eg,
+    backward_pawn_mg,
+    backward_pawn_eg,
+    connected_pawn_mg,
+    connected_pawn_eg,
+    passed_pawn_mg,
+    passed_pawn_eg,
+    passed_king_distance_eg,
+    passed_free_path_mg,
+    passed_free_path_eg,
+    pawn_shield_mg,
+    pawn_storm_mg,
+    king_semi_open_file_mg,
+    king_open_file_mg,
+    king_safety_table,
+    tempo_bonus,
+);
+
+/// Lists every `Params` field for the config file and as UCI spin options.
+/// Fields missing from a file keep their default; unknown keys are an error so
+/// typos do not go unnoticed.
+///
+/// `field in min..=max step s` is the range of the field, or of each of its
+/// entries, in the field's own units. Config values are checked against it and
+/// `setoption` clamps to it. Spin options are integers, so `scale k` makes one
+/// unit worth `k` spin steps and keeps fractional fields at `1/k` precision.
+macro_rules! config_fields {
+    ($($field:ident in $min:literal ..= $max:literal step $step:literal $(scale $scale:literal)?),* $(,)?) => {
+        impl Params {
+            pub fn to_config(&self) -> String {
+                let mut out = String::new();
+                $(
+                    out.push_str(concat!(stringify!($field), " = "));
+                    self.$field.write(&mut out);
+                    out.push('\n');
+                )*
+                out
+            }
+
+            pub fn from_config(text: &str) -> Result<Params, String> {
+                let mut params = Params::default();
+
+                for (line, key, value) in config::parse(text)? {
+                    let result = match key.as_str() {
+                        $(stringify!($field) => params.$field.read(&value)
+                            .and_then(|()| check_range(&params.$field, $min as f64, $max as f64)),)*
+                        _ => Err("unknown parameter".to_string()),
+                    };
+
+                    result.map_err(|e| format!("line {}: {}: {}", line, key, e))?;
+                }
+
+                Ok(params)
+            }
+
+            /// One spin option per scalar field and per array entry, with the
+            /// current values as defaults.
+            pub fn spin_options(&self) -> Vec<SpinOption> {
+                let mut options = Vec::new();
+                $(
+                    let range = SpinRange::new($min as f64, $max as f64, $step as f64, 1.0 $(* $scale as f64)?);
+                    push_spin_options(&mut options, stringify!($field), &self.$field, &range);
+                )*
+                options
+            }
+
+            /// Sets the option called `name`, clamped to its range. Returns false
+            /// if there is no such option.
+            pub fn set_spin_option(&mut self, name: &str, value: i32) -> bool {
+                $(
+                    if let Some(index) = spin_index(name, stringify!($field), &self.$field) {
+                        let range = SpinRange::new($min as f64, $max as f64, $step as f64, 1.0 $(* $scale as f64)?);
+                        self.$field.spin_set(index, value.clamp(range.spin(range.min), range.spin(range.max)), range.scale);
+                        return true;
+                    }
+                )*
+                false
+            }
+        }
+    };
+}
+
+config_fields!(
+    material_mg in 0..=1500 step 10,
+    material_eg in 0..=1500 step 10,
+    material_weight in 0.0..=2.0 step 0.05 scale 100,
+    pst_mg in -300..=300 step 5,
+    pst_eg in -300..=300 step 5,
+    pst_weight in 0.0..=2.0 step 0.05 scale 100,
+    mobility_mg in -10..=20 step 1,
+    mobility_eg in -10..=20 step 1,
+    doubled_pawn_mg in -100..=100 step 2,
+    doubled_pawn_eg in -100..=100 step 2,
+    isolated_pawn_mg in -100..=100 step 2,
+    isolated_pawn_eg in -100..=100 step 2,
+    backward_pawn_mg in -100..=100 step 2,
+    backward_pawn_eg in -100..=100 step 2,
+    connected_pawn_mg in -20..=40 step 1,
+    connected_pawn_eg in -20..=40 step 1,
+    passed_pawn_mg in -50..=300 step 5,
+    passed_pawn_eg in -50..=300 step 5,
+    passed_king_distance_eg in -20..=40 step 1,
+    passed_free_path_mg in -50..=100 step 2,
+    passed_free_path_eg in -50..=100 step 2,
+    pawn_shield_mg in -100..=100 step 2,
+    pawn_storm_mg in -100..=100 step 2,
+    king_semi_open_file_mg in -100..=50 step 2,
+    king_open_file_mg in -100..=50 step 2,
+    king_attack_weight in 0..=20 step 1,
+    safe_check_weight in 0..=20 step 1,
+    king_safety_table in 0..=1000 step 10,
+    king_safety_weight in 0.0..=2.0 step 0.05 scale 100,
+    tempo_bonus in 0..=40 step 2,
+    aspiration_window in 5..=100 step 5,
+    null_move_reduction in 1..=6 step 1,
+    null_move_depth_divisor in 2..=12 step 1,
+    null_move_eval_divisor in 50..=400 step 20,
+    null_move_verify_depth in 4..=20 step 1,
+    lmr_base in 0.0..=4.0 step 0.05 scale 100,
+    lmr_divisor in 0.5..=10.0 step 0.1 scale 100,
+    see_quiet_margin in 0..=100 step 5,
+);
+
+fn check_range(value: &impl ConfigValue, min: f64, max: f64) -> Result<(), String> {
+    if value.in_range(min, max) {
+        Ok(())
+    } else {
+        Err(format!("out of range, expected {} to {}", min, max))
+    }
+}
+
+impl Params {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Params> {
+        Params::from_config(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_config())
+    }
+}
+
+/// Whether `uci` lists the spin options. Off by default to keep GUIs tidy; they
+/// are always accepted by `setoption`.
+pub const EXPOSE_SPIN_OPTIONS: bool = cfg!(feature = "spsa");
+
+/// A `Params` entry exposed as a UCI spin option, for SPSA tuning. Values are
+/// in spin units, the field value times its scale.
+pub struct SpinOption {
+    pub name: String,
+    pub value: i32,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+}
+
+/// A field's range from `config_fields!`, in the field's own units.
+struct SpinRange {
+    min: f64,
+    max: f64,
+    step: f64,
+    scale: f64,
+}
+
+impl SpinRange {
+    fn new(min: f64, max: f64, step: f64, scale: f64) -> Self {
+        Self { min, max, step, scale }
+    }
+
+    /// `value` in spin units.
+    fn spin(&self, value: f64) -> i32 {
+        (value * self.scale).round() as i32
+    }
+}
+
+/// Fields the spin options read and write, entry by entry for arrays, which
+/// are flattened row by row like `Weights`.
+pub trait SpinValue {
+    fn spin_len(&self) -> usize;
+    fn spin_get(&self, index: usize, scale: f64) -> i32;
+    fn spin_set(&mut self, index: usize, value: i32, scale: f64);
+}
+
+impl SpinValue for f32 {
+    fn spin_len(&self) -> usize {
+        1
+    }
+
+    fn spin_get(&self, _index: usize, scale: f64) -> i32 {
+        (*self as f64 * scale).round() as i32
+    }
+
+    fn spin_set(&mut self, _index: usize, value: i32, scale: f64) {
+        *self = (value as f64 / scale) as f32;
+    }
+}
+
+macro_rules! integer_spin_value {
+    ($($ty:ty),*) => {$(
+        impl SpinValue for $ty {
+            fn spin_len(&self) -> usize {
+                1
+            }
+
+            fn spin_get(&self, _index: usize, scale: f64) -> i32 {
+                (*self as f64 * scale).round() as i32
+            }
+
+            // Negative values saturate to zero for unsigned fields
+            fn spin_set(&mut self, _index: usize, value: i32, scale: f64) {
+                *self = (value as f64 / scale).round() as $ty;
+            }
+        }
+    )*};
+}
+
+integer_spin_value!(i32, usize);
+
+impl<T: SpinValue, const N: usize> SpinValue for [T; N] {
+    fn spin_len(&self) -> usize {
+        self.iter().map(SpinValue::spin_len).sum()
+    }
+
+    fn spin_get(&self, index: usize, scale: f64) -> i32 {
+        let len = self[0].spin_len();
+        self[index / len].spin_get(index % len, scale)
+    }
+
+    fn spin_set(&mut self, index: usize, value: i32, scale: f64) {
+        let len = self[0].spin_len();
+        self[index / len].spin_set(index % len, value, scale)
+    }
+}
+
+/// Options are named after the field, with `_index` appended for array entries.
+fn push_spin_options(options: &mut Vec<SpinOption>, field: &str, value: &impl SpinValue, range: &SpinRange) {
+    let len = value.spin_len();
+
+    for index in 0..len {
+        options.push(SpinOption {
+            name: if len == 1 { field.to_string() } else { format!("{}_{}", field, index) },
+            value: value.spin_get(index, range.scale),
+            min: range.spin(range.min),
+            max: range.spin(range.max),
+            step: range.spin(range.step).max(1),
+        });
+    }
+}
+
+/// The entry of `field` that the option `name` stands for, if any.
+fn spin_index(name: &str, field: &str, value: &impl SpinValue) -> Option<usize> {
+    let rest = match name.get(..field.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(field) => &name[field.len()..],
+        _ => return None,
+    };
+    let len = value.spin_len();
+
+    if len == 1 {
+        return rest.is_empty().then_some(0);
+    }
+
+    rest.strip_prefix('_')?.parse().ok().filter(|&index| index < len)
+}
+
+// Attack units to centipawns, rising quadratically before levelling off.
+#[rustfmt::skip]
+const KING_SAFETY_TABLE: [i32; KING_SAFETY_TABLE_SIZE] = [
+      0,   0,   1,   2,   3,   5,   7,   9,  12,  15,
+     18,  22,  26,  30,  35,  39,  44,  50,  56,  62,
+     68,  75,  82,  85,  89,  97, 105, 113, 122, 131,
+    140, 150, 169, 180, 191, 202, 213, 225, 237, 248,
+    260, 272, 283, 295, 307, 319, 330, 342, 354, 366,
+    377, 389, 401, 412, 424, 436, 448, 459, 471, 483,
+    494, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+fn pst_to_f32(table: &[[i32; 64]; 6]) -> [[f32; 64]; 6] {
+    table.map(|squares| squares.map(|v| v as f32))
+}
+
+// Piece-square tables, P, N, B, R, Q, K. Laid out as seen from white with a8
+// first, so a white piece on `sq` reads index `sq ^ 56` and a black one `sq`.
+#[rustfmt::skip]
+const PST_MG: [[i32; 64]; 6] = [
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98, 134,  61,  95,  68, 126,  34, -11,
+         -6,   7,  26,  31,  65,  56,  25, -20,
+        -14,  13,   6,  21,  23,  12,  17, -23,
+        -27,  -2,  -5,  12,  17,   6,  10, -25,
+        -26,  -4,  -4, -10,   3,   3,  33, -12,
+        -35,  -1, -20, -23, -15,  24,  38, -22,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+       -167, -89, -34, -49,  61, -97, -15,-107,
+        -73, -41,  72,  36,  23,  62,   7, -17,
+        -47,  60,  37,  65,  84, 129,  73,  44,
+         -9,  17,  19,  53,  37,  69,  18,  22,
+        -13,   4,  16,  13,  28,  19,  21,  -8,
+        -23,  -9,  12,  10,  19,  17,  25, -16,
+        -29, -53, -12,  -3,  -1,  18, -14, -19,
+       -105, -21, -58, -33, -17, -28, -19, -23,
+    ],
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    [
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[rustfmt::skip]
+const PST_EG: [[i32; 64]; 6] = [
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    [
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+    [
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config;
+
+    #[test]
+    fn every_field_has_spin_options() {
+        let params = Params::default();
+        let options = params.spin_options();
+
+        for (_, key, _) in config::parse(&params.to_config()).unwrap() {
+            let entry = format!("{}_0", key);
+            assert!(options.iter().any(|o| o.name == key || o.name == entry), "{} has no spin option", key);
         }
+
+        assert_eq!(options.iter().filter(|o| o.name.starts_with("pst_mg_")).count(), 6 * 64);
+    }
+
+    #[test]
+    fn fractional_fields_keep_their_precision() {
+        let mut params = Params::default();
+        let lmr_base = params.spin_options().into_iter().find(|o| o.name == "lmr_base").unwrap();
+        assert_eq!((lmr_base.value, lmr_base.min, lmr_base.max, lmr_base.step), (75, 0, 400, 5));
+
+        assert!(params.set_spin_option("lmr_divisor", 180));
+        assert_eq!(params.lmr_divisor, 1.8);
+    }
+
+    #[test]
+    fn sets_array_entries_by_flat_index() {
+        let mut params = Params::default();
+
+        assert!(params.set_spin_option("pst_mg_130", 17));
+        assert!(params.set_spin_option("MATERIAL_EG_4", 950));
+        assert_eq!((params.pst_mg[2][2], params.material_eg[4]), (17.0, 950.0));
+
+        assert!(params.set_spin_option("tempo_bonus", 1000));
+        assert_eq!(params.tempo_bonus, 40.0);
+
+        assert!(!params.set_spin_option("pst_mg_384", 0));
+        assert!(!params.set_spin_option("pst_mg", 0));
+        assert!(!params.set_spin_option("tempo_bonus_0", 0));
     }
 }
@@ -0,0 +1,222 @@
+use shakmaty::attacks::pawn_attacks;
+use shakmaty::zobrist::{Zobrist64, ZobristValue};
+use shakmaty::{Bitboard, Board, Chess, Color, Piece, Position, Role, Square};
+
+use crate::engine::params::Params;
+
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const RANK_1: u64 = 0xff;
+
+/// Cached structure terms for one pawn configuration. Passed pawns are kept so
+/// the king and piece dependent parts can be added on every evaluation.
+#[derive(Clone, Copy, Default)]
+struct PawnEntry {
+    key: u64,
+    mg: f32,
+    eg: f32,
+    passed: [Bitboard; 2],
+}
+
+/// Per-thread cache of pawn structure evaluations, keyed by a pawn-only Zobrist key.
+pub struct PawnTable {
+    entries: Box<[PawnEntry]>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE].into_boxed_slice(),
+        }
+    }
+
+    /// `key` is `pawn_key(board)`, usually maintained incrementally by search.
+    #[inline(always)]
+    fn probe(&mut self, board: &Board, params: &Params, key: u64) -> PawnEntry {
+        let slot = &mut self.entries[key as usize & (PAWN_TABLE_SIZE - 1)];
+
+        // An empty slot shares key 0 with a pawnless board; both score zero
+        if slot.key != key {
+            *slot = evaluate_structure(board, params, key);
+        }
+
+        *slot
+    }
+}
+
+/// Zobrist key over the pawns only.
+#[inline(always)]
+pub fn pawn_key(board: &Board) -> u64 {
+    let mut key = Zobrist64::default();
+
+    for color in [Color::White, Color::Black] {
+        let piece = Piece { color, role: Role::Pawn };
+
+        for sq in board.by_piece(piece) {
+            key ^= Zobrist64::zobrist_for_piece(sq, piece);
+        }
+    }
+
+    key.0
+}
+
+/// Pawn structure score (mg, eg) from white's point of view. The table comes
+/// with the pawn key of `pos`.
+#[inline(always)]
+pub fn evaluate_pawns(pos: &Chess, params: &Params, table: Option<(&mut PawnTable, u64)>) -> (f32, f32) {
+    let board = pos.board();
+    let entry = match table {
+        Some((table, key)) => table.probe(board, params, key),
+        None => evaluate_structure(board, params, 0),
+    };
+
+    let mut mg = entry.mg;
+    let mut eg = entry.eg;
+
+    for color in [Color::White, Color::Black] {
+        let (passer_mg, passer_eg) = passed_pawn_extras(board, params, color, entry.passed[color as usize]);
+        let sign = if color == Color::White { 1.0 } else { -1.0 };
+
+        mg += sign * passer_mg;
+        eg += sign * passer_eg;
+    }
+
+    (mg, eg)
+}
+
+fn evaluate_structure(board: &Board, params: &Params, key: u64) -> PawnEntry {
+    let mut entry = PawnEntry { key, ..PawnEntry::default() };
+
+    for color in [Color::White, Color::Black] {
+        let own = board.by_piece(Piece { color, role: Role::Pawn });
+        let their = board.by_piece(Piece { color: !color, role: Role::Pawn });
+
+        let mut mg = 0.0;
+        let mut eg = 0.0;
+        let mut passed = Bitboard::EMPTY;
+
+        for sq in own {
+            let file = sq.file() as u32;
+            let rank = relative_rank(color, sq);
+            let ahead = forward_ranks(color, sq);
+            let adjacent = adjacent_files(file);
+
+            let supported = (own & pawn_attacks(!color, sq)).any();
+            let phalanx = (own.0 & adjacent & (RANK_1 << (8 * sq.rank() as u32))) != 0;
+            let isolated = own.0 & adjacent == 0;
+
+            if own.0 & file_mask(file) & ahead != 0 {
+                mg += params.doubled_pawn_mg;
+                eg += params.doubled_pawn_eg;
+            }
+
+            if isolated {
+                mg += params.isolated_pawn_mg;
+                eg += params.isolated_pawn_eg;
+            } else if !supported && !phalanx && own.0 & adjacent & !ahead == 0 {
+                // Nothing can come up to defend it and its stop square is covered
+                let stop = sq.offset(if color == Color::White { 8 } else { -8 });
+
+                if stop.is_some_and(|stop| (their & pawn_attacks(color, stop)).any()) {
+                    mg += params.backward_pawn_mg;
+                    eg += params.backward_pawn_eg;
+                }
+            }
+
+            if supported || phalanx {
+                mg += params.connected_pawn_mg * rank as f32;
+                eg += params.connected_pawn_eg * rank as f32;
+            }
+
+            if their.0 & (file_mask(file) | adjacent) & ahead == 0 {
+                passed.add(sq);
+                mg += params.passed_pawn_mg[rank];
+                eg += params.passed_pawn_eg[rank];
+            }
+        }
+
+        let sign = if color == Color::White { 1.0 } else { -1.0 };
+        entry.mg += sign * mg;
+        entry.eg += sign * eg;
+        entry.passed[color as usize] = passed;
+    }
+
+    entry
+}
+
+/// The parts of a passed pawn's value that depend on more than pawns: how much
+/// closer our king is to its path than theirs, and whether the way is clear.
+/// Both grow with the pawn's rank.
+#[inline(always)]
+fn passed_pawn_extras(board: &Board, params: &Params, color: Color, passed: Bitboard) -> (f32, f32) {
+    let mut mg = 0.0;
+    let mut eg = 0.0;
+
+    let (Some(our_king), Some(their_king)) = (board.king_of(color), board.king_of(!color)) else {
+        return (0.0, 0.0);
+    };
+
+    for sq in passed {
+        let scale = rank_scale(relative_rank(color, sq));
+        let Some(stop) = sq.offset(if color == Color::White { 8 } else { -8 }) else {
+            continue;
+        };
+
+        let distance = their_king.distance(stop) as f32 - our_king.distance(stop) as f32;
+        eg += params.passed_king_distance_eg * distance * scale;
+
+        if board.occupied().0 & file_mask(sq.file() as u32) & forward_ranks(color, sq) == 0 {
+            mg += params.passed_free_path_mg * scale;
+            eg += params.passed_free_path_eg * scale;
+        }
+    }
+
+    (mg, eg)
+}
+
+#[inline(always)]
+fn relative_rank(color: Color, sq: Square) -> usize {
+    match color {
+        Color::White => sq.rank() as usize,
+        Color::Black => 7 - sq.rank() as usize,
+    }
+}
+
+/// 0 for a pawn on its second rank, 1 on its seventh.
+#[inline(always)]
+fn rank_scale(rank: usize) -> f32 {
+    rank.saturating_sub(1) as f32 / 5.0
+}
+
+#[inline(always)]
+pub(crate) fn file_mask(file: u32) -> u64 {
+    FILE_A << file
+}
+
+#[inline(always)]
+fn adjacent_files(file: u32) -> u64 {
+    let mut mask = 0;
+
+    if file > 0 {
+        mask |= file_mask(file - 1);
+    }
+    if file < 7 {
+        mask |= file_mask(file + 1);
+    }
+
+    mask
+}
+
+/// Every rank strictly in front of `sq` from `color`'s point of view.
+#[inline(always)]
+pub(crate) fn forward_ranks(color: Color, sq: Square) -> u64 {
+    let rank = sq.rank() as u32;
+
+    match color {
+        Color::White if rank == 7 => 0,
+        Color::White => !0 << (8 * (rank + 1)),
+        Color::Black if rank == 0 => 0,
+        Color::Black => !0 >> (8 * (8 - rank)),
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use shakmaty::fen::Fen;
+use shakmaty::zobrist::Zobrist64;
+use shakmaty::{perft as count_leaves, CastlingMode, Chess, EnPassantMode, Move, Position};
+
+use crate::engine::make_move::update_hash;
+use crate::engine::utility::read_position_from_fen;
+
+/// Counts the leaves `depth` plies below `pos`. With `check_hash` the tree is
+/// walked move by move, carrying the Zobrist hash incrementally and comparing
+/// it with a full recompute at every node; the error names the first node
+/// where they differ.
+pub fn perft(pos: &Chess, depth: u32, check_hash: bool) -> Result<u64, String> {
+    if !check_hash {
+        return Ok(count_leaves(pos, depth));
+    }
+
+    perft_hashed(pos, full_hash(pos), depth)
+}
+
+/// Leaf counts below each legal root move, in move generation order.
+pub fn divide(pos: &Chess, depth: u32, check_hash: bool) -> Result<Vec<(Move, u64)>, String> {
+    if depth == 0 {
+        return Ok(Vec::new());
+    }
+
+    let hash = full_hash(pos);
+
+    pos.legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let child = play(pos, mv);
+
+            let nodes = if check_hash {
+                perft_hashed(&child, next_hash(pos, hash, mv, &child)?, depth - 1)?
+            } else {
+                count_leaves(&child, depth - 1)
+            };
+
+            Ok((mv, nodes))
+        })
+        .collect()
+}
+
+/// Runs an EPD perft suite, lines of the form `<fen> ;D1 20 ;D2 400 ...`.
+/// Depths above `max_depth` are skipped. Prints one line per position and
+/// returns the number of positions that failed.
+pub fn run_suite(path: &str, max_depth: Option<u32>, check_hash: bool) -> io::Result<usize> {
+    let reader = BufReader::new(File::open(path)?);
+    let start = Instant::now();
+    let mut total_nodes = 0;
+    let mut positions = 0;
+    let mut failed = 0;
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or("").trim();
+
+        if fen.is_empty() || fen.starts_with('#') {
+            continue;
+        }
+
+        positions += 1;
+
+        let Some(pos) = read_position_from_fen(fen) else {
+            println!("{:>4} FAIL invalid fen {}", positions, fen);
+            failed += 1;
+            continue;
+        };
+
+        let mut error = None;
+
+        for field in fields {
+            let mut parts = field.split_whitespace();
+            let depth = parts.next().and_then(|d| d.strip_prefix('D')).and_then(|d| d.parse::<u32>().ok());
+            let expected = parts.next().and_then(|n| n.parse::<u64>().ok());
+
+            let (Some(depth), Some(expected)) = (depth, expected) else {
+                continue;
+            };
+
+            if max_depth.is_some_and(|max| depth > max) {
+                continue;
+            }
+
+            match perft(&pos, depth, check_hash) {
+                Ok(nodes) if nodes == expected => total_nodes += nodes,
+                Ok(nodes) => {
+                    error = Some(format!("depth {} expected {} got {}", depth, expected, nodes));
+                    break;
+                }
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        match error {
+            None => println!("{:>4} ok   {}", positions, fen),
+            Some(e) => {
+                println!("{:>4} FAIL {}: {}", positions, fen, e);
+                failed += 1;
+            }
+        }
+    }
+
+    let elapsed = start.elapsed();
+    println!("passed {}/{}", positions - failed, positions);
+    println!("nodes {} time {} nps {}", total_nodes, elapsed.as_millis(), nps(total_nodes, elapsed));
+
+    Ok(failed)
+}
+
+/// Nodes per second, safe for searches too quick to register on the clock.
+pub fn nps(nodes: u64, elapsed: Duration) -> u64 {
+    (nodes as u128 * 1_000_000 / elapsed.as_micros().max(1)) as u64
+}
+
+fn perft_hashed(pos: &Chess, hash: Zobrist64, depth: u32) -> Result<u64, String> {
+    if depth == 0 {
+        return Ok(1);
+    }
+
+    let mut nodes = 0;
+
+    for mv in pos.legal_moves() {
+        let child = play(pos, mv);
+        nodes += perft_hashed(&child, next_hash(pos, hash, mv, &child)?, depth - 1)?;
+    }
+
+    Ok(nodes)
+}
+
+/// Updates `hash` the way search does and checks it against `child` hashed
+/// from scratch. A move the update cannot handle is an error, never a pass.
+fn next_hash(pos: &Chess, hash: Zobrist64, mv: Move, child: &Chess) -> Result<Zobrist64, String> {
+    let full = full_hash(child);
+    let move_uci = mv.to_uci(CastlingMode::Standard);
+
+    let Some(incremental) = update_hash(pos, hash.0, mv, child).map(Zobrist64) else {
+        return Err(format!(
+            "no incremental hash update for {} from {}",
+            move_uci,
+            Fen::from_position(pos, EnPassantMode::Legal)
+        ));
+    };
+
+    if incremental != full {
+        return Err(format!(
+            "hash mismatch after {} from {}: {:016x} != {:016x}",
+            move_uci,
+            Fen::from_position(pos, EnPassantMode::Legal),
+            incremental.0,
+            full.0
+        ));
+    }
+
+    Ok(incremental)
+}
+
+fn full_hash(pos: &Chess) -> Zobrist64 {
+    pos.zobrist_hash(EnPassantMode::Legal)
+}
+
+fn play(pos: &Chess, mv: Move) -> Chess {
+    let mut child = pos.clone();
+    child.play_unchecked(mv);
+    child
+}
@@ -1,89 +1,160 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use shakmaty::{Chess, Position};
 
 
+use crate::engine::eval::evaluate;
+use crate::engine::make_move::SearchBoard;
 use crate::engine::params::Params;
+use crate::engine::pawns::PawnTable;
+use crate::engine::search::limits::SearchLimits;
 use crate::engine::search::ordering::MoveOrdering;
 use crate::engine::search::pv::{MultiPv, PvTable};
+use crate::engine::search::reductions::ReductionTable;
+use crate::engine::search::report::{SearchReporter, SilentReporter};
 use crate::engine::search::search::SearchStats;
+use crate::engine::tablebase::{Tablebase, DEFAULT_PROBE_DEPTH};
 use crate::engine::tt::TranspositionTable;
+use crate::engine::types::{Score, MAX_PLY};
 
 pub struct SearchContext<'a> {
     pub params: &'a Params,
-    pub ordering: &'a MoveOrdering,
+    pub ordering: MoveOrdering,
+    pub pawns: PawnTable,
+    /// The current line. Search makes and unmakes every move through it.
+    pub board: SearchBoard,
+    pub reductions: ReductionTable,
     pub pv: PvTable,
     pub stats: SearchStats,
     pub multipv: MultiPv,
-    pub repetition_stack: Vec<u64>,
-    pub tt: &'a mut TranspositionTable,
-    pub hash : u64,
+    pub tt: &'a TranspositionTable,
+    pub stop: &'a AtomicBool,
+    /// Set while pondering: the time limit only starts to count once it clears.
+    pub pondering: Option<&'a AtomicBool>,
+    pub stopped: bool,
+    pub completed_depth: usize,
+    pub limits: SearchLimits,
+    pub start: Instant,
+    pub reporter: &'a dyn SearchReporter,
+    pub thread_id: usize,
+    pub shared_nodes: Option<Arc<AtomicU64>>,
+    pub shared_tbhits: Option<Arc<AtomicU64>>,
+    pub tablebase: Option<Arc<Tablebase>>,
+    /// Positions with as many pieces as the largest tables are only probed
+    /// this far from the leaves; smaller ones always are.
+    pub tb_probe_depth: usize,
 }
 
 impl<'a> SearchContext<'a> {
     pub fn new(
         params: &'a Params,
-        ordering: &'a MoveOrdering,
         multipv_count: usize,
-        tt: &'a mut TranspositionTable,
-        hash : u64
+        tt: &'a TranspositionTable,
+        stop: &'a AtomicBool,
     ) -> Self {
         Self {
             params,
-            ordering,
-            pv: PvTable::new(64),
+            ordering: MoveOrdering::new(&params.material_mg),
+            pawns: PawnTable::new(),
+            board: SearchBoard::new(),
+            reductions: ReductionTable::new(params),
+            pv: PvTable::new(MAX_PLY),
             stats: SearchStats::default(),
             multipv: MultiPv::new(multipv_count),
-            repetition_stack: Vec::with_capacity(256),
             tt ,
-            hash,
+            stop,
+            pondering: None,
+            stopped: false,
+            completed_depth: 0,
+            limits: SearchLimits::new(),
+            start: Instant::now(),
+            reporter: &SilentReporter,
+            thread_id: 0,
+            shared_nodes: None,
+            shared_tbhits: None,
+            tablebase: None,
+            tb_probe_depth: DEFAULT_PROBE_DEPTH,
         }
     }
-    #[inline(always)]
-    pub fn is_threefold(&mut self, pos: &Chess) -> bool {
-
-        let mut count = 0;
 
-        let current = self.repetition_stack.last().unwrap_or(&0);
-        let len = self.repetition_stack.len();
+    /// Polls the shared stop flag and the node and time limits. The node limit
+    /// counts the helpers' nodes too. The clock never cuts the main thread's
+    /// first iteration short, so a timed search has a searched move to report;
+    /// `stop` and the node limit apply from the first node on.
+    #[inline(always)]
+    pub fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
 
-        if len == 0{
-            return false;
+        // Helpers publish their node count for the main thread's info lines
+        if self.thread_id != 0 && self.stats.nodes & 1023 == 0 {
+            if let Some(shared) = &self.shared_nodes {
+                shared.fetch_add(1024, Ordering::Relaxed);
+            }
         }
 
-        // Avoid underflow
-        let start = len.saturating_sub(pos.halfmoves() as usize + 1);
+        if let Some(nodes) = self.limits.nodes {
+            if self.total_nodes() >= nodes {
+                self.stopped = true;
+                return true;
+            }
+        }
 
-        // Scan backwards skipping last position
-        for &hash in self.repetition_stack[start..len-1].iter().rev() {
+        if self.stats.nodes & 1023 == 0 {
+            let timed_out = (self.completed_depth > 0 || self.thread_id != 0) && self.out_of_time();
 
-            if hash == *current {
-                count += 1;
-                if count >= 2 {
-                    return true; // 3-fold repetition
-                }
+            if timed_out || self.stop.load(Ordering::Relaxed) {
+                self.stopped = true;
             }
         }
 
-        false
+        self.stopped
     }
+
+    /// Whether the time limit has passed. It is measured from the start of the
+    /// search, so after a `ponderhit` the time spent pondering counts too.
     #[inline(always)]
-    pub fn is_50_moves(&self,pos: &Chess) -> bool {
-        pos.halfmoves()> 100
+    pub fn out_of_time(&self) -> bool {
+        let pondering = self.pondering.is_some_and(|p| p.load(Ordering::Relaxed));
+
+        !pondering && self.limits.time_limit.is_some_and(|limit| self.start.elapsed() >= limit)
     }
-    #[inline(always)]
-    pub fn _init_history(&mut self, hash : u64) {
-        self.repetition_stack.clear();
-        self.repetition_stack.push(hash);
+
+    /// Nodes searched by this thread plus whatever the helpers have reported so far.
+    pub fn total_nodes(&self) -> u64 {
+        self.stats.nodes + self.shared_nodes.as_ref().map_or(0, |n| n.load(Ordering::Relaxed))
     }
 
-    #[inline(always)]
-    pub fn increase_history(&mut self, hash : u64) {
-        self.repetition_stack.push(hash);
+    /// Tablebase hits of this thread and the helpers.
+    pub fn total_tbhits(&self) -> u64 {
+        self.stats.tbhits + self.shared_tbhits.as_ref().map_or(0, |n| n.load(Ordering::Relaxed))
     }
 
+    /// Counts a tablebase hit. Helpers publish theirs right away, they are rare.
     #[inline(always)]
-    pub fn decrease_history(&mut self) {
-        self.repetition_stack.pop();
+    pub fn record_tbhit(&mut self) {
+        self.stats.tbhits += 1;
+
+        if self.thread_id != 0 {
+            if let Some(shared) = &self.shared_tbhits {
+                shared.fetch_add(1, Ordering::Relaxed);
+            }
+        }
     }
 
+    #[inline(always)]
+    pub fn is_50_moves(&self,pos: &Chess) -> bool {
+        pos.halfmoves()> 100
+    }
 
+    /// Static evaluation of `pos`, the position at the board's current ply.
+    #[inline(always)]
+    pub fn evaluate(&mut self, pos: &Chess) -> Score {
+        match self.board.nnue() {
+            Some(nnue) => nnue.evaluate(self.board.ply(), pos.turn()),
+            None => evaluate(pos, self.params, &mut self.pawns, self.board.pawn_key()),
+        }
+    }
 }
@@ -0,0 +1,35 @@
+use std::time::Duration;
+use shakmaty::Move;
+
+pub const MAX_DEPTH: usize = 64;
+
+/// Stop conditions for one `go` command. The search ends at whichever is hit first.
+#[derive(Clone)]
+pub struct SearchLimits {
+    pub max_depth: usize,
+    pub time_limit: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub infinite: bool,
+    pub searchmoves: Vec<Move>,
+}
+
+impl SearchLimits {
+    pub fn new() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+            time_limit: None,
+            nodes: None,
+            mate: None,
+            infinite: false,
+            searchmoves: Vec::new(),
+        }
+    }
+
+    pub fn depth(max_depth: usize) -> Self {
+        Self {
+            max_depth: max_depth.clamp(1, MAX_DEPTH),
+            ..Self::new()
+        }
+    }
+}
@@ -1,4 +1,11 @@
+#[allow(clippy::module_inception)]
 pub mod search;
 pub mod pv;
 pub mod ordering;
+pub mod picker;
+pub mod limits;
+pub mod report;
+pub mod reductions;
+pub mod threads;
+pub mod bench;
 pub(crate) mod context;
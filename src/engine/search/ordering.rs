@@ -1,8 +1,22 @@
-use shakmaty::{Chess, Move, MoveList, Position, Role};
+use shakmaty::{Chess, Color, Move, Position, Role};
 
-#[derive(Clone)]
+use crate::engine::types::MAX_PLY;
+
+// History values are kept within +-MAX_HISTORY by the gravity update.
+pub const MAX_HISTORY: i32 = 16384;
+
+/// [piece of the previous move][its target square][role moving now][target square]
+type ContinuationHistory = [[[[i16; 64]; 6]; 64]; 12];
+
+/// Per-thread move ordering state. Quiet moves are ranked by two killers per
+/// ply, the countermove to the opponent's last move, butterfly history and
+/// continuation history; all of it is learned during the search.
 pub struct MoveOrdering {
     mvv_lva: [[i32; 6]; 6],
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    history: Box<[[[i16; 64]; 64]; 2]>,
+    countermoves: Box<[[Option<Move>; 64]; 12]>,
+    continuation: Box<ContinuationHistory>,
 }
 
 impl MoveOrdering {
@@ -18,49 +32,18 @@ impl MoveOrdering {
             }
         }
 
-        Self { mvv_lva: table }
-    }
-
-    #[inline(always)]
-    pub fn order_moves(
-        &self,
-        pos: &Chess,
-        pv_move: Option<&Move>,
-        tt_move: Option<&Move>,
-        moves: &mut MoveList,
-    ) {
-        // 1. PV move first
-        if let Some(pv) = pv_move {
-            if let Some(idx) = moves.iter().position(|m| m == pv) {
-                moves.swap(0, idx);
-            }
+        Self {
+            mvv_lva: table,
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            countermoves: Box::new([[None; 64]; 12]),
+            continuation: vec![[[[0; 64]; 6]; 64]; 12]
+                .into_boxed_slice()
+                .try_into()
+                .unwrap(),
         }
-
-        // 2. TT move second (if not same as PV)
-        if let Some(tt) = tt_move {
-            if Some(tt) != pv_move {
-                if let Some(idx) = moves.iter().position(|m| m == tt) {
-                    let insert = if pv_move.is_some() { 1 } else { 0 };
-                    moves.swap(insert, idx);
-                }
-            }
-        }
-
-        let (mut captures, quiets): (Vec<_>, Vec<_>) =
-            moves.drain(..).partition(|mv| mv.is_capture());
-
-        self.order_captures(pos, &mut captures);
-
-        moves.extend(captures);
-        moves.extend(quiets);
     }
 
-
-
-    #[inline(always)]
-    pub fn order_captures(&self, pos: &Chess, moves: &mut [Move]) {
-        moves.sort_by_key(|mv| -self.mvv_lva_score(pos, mv));
-    }
     #[inline(always)]
     pub fn mvv_lva_score(&self, pos: &Chess, mv: &Move) -> i32 {
         let board = pos.board();
@@ -78,4 +61,98 @@ impl MoveOrdering {
 
         self.mvv_lva[attacker][victim]
     }
+
+    #[inline(always)]
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers[ply]
+    }
+
+    #[inline(always)]
+    pub fn countermove(&self, turn: Color, prev_move: &Move) -> Option<Move> {
+        self.countermoves[piece_index(!turn, prev_move.role())][prev_move.to() as usize]
+    }
+
+    /// History plus continuation history, the ordering score of a plain quiet move.
+    #[inline(always)]
+    pub fn quiet_score(&self, turn: Color, prev_move: Option<&Move>, mv: &Move) -> i32 {
+        self.history_score(turn, mv) + self.continuation_score(turn, prev_move, mv)
+    }
+
+    #[inline(always)]
+    pub fn is_killer(&self, ply: usize, mv: &Move) -> bool {
+        self.killers[ply].iter().any(|k| k.as_ref() == Some(mv))
+    }
+
+    #[inline(always)]
+    pub fn history_score(&self, turn: Color, mv: &Move) -> i32 {
+        self.history[turn as usize][from_index(mv)][mv.to() as usize] as i32
+    }
+
+    #[inline(always)]
+    fn continuation_score(&self, turn: Color, prev_move: Option<&Move>, mv: &Move) -> i32 {
+        match prev_move {
+            Some(prev) => {
+                self.continuation[piece_index(!turn, prev.role())][prev.to() as usize]
+                    [mv.role() as usize - 1][mv.to() as usize] as i32
+            }
+            None => 0,
+        }
+    }
+
+    /// Rewards the quiet move that caused a beta cutoff and penalises the
+    /// quiets searched before it.
+    pub fn update_quiet_stats(
+        &mut self,
+        turn: Color,
+        ply: usize,
+        prev_move: Option<&Move>,
+        best: &Move,
+        tried: &[Move],
+        depth: usize,
+    ) {
+        if self.killers[ply][0].as_ref() != Some(best) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(*best);
+        }
+
+        if let Some(prev) = prev_move {
+            self.countermoves[piece_index(!turn, prev.role())][prev.to() as usize] = Some(*best);
+        }
+
+        let bonus = (depth * depth).min(1200) as i32;
+
+        self.update_history(turn, prev_move, best, bonus);
+        for mv in tried.iter().filter(|mv| *mv != best) {
+            self.update_history(turn, prev_move, mv, -bonus);
+        }
+    }
+
+    #[inline(always)]
+    fn update_history(&mut self, turn: Color, prev_move: Option<&Move>, mv: &Move, bonus: i32) {
+        let entry = &mut self.history[turn as usize][from_index(mv)][mv.to() as usize];
+        apply_gravity(entry, bonus);
+
+        if let Some(prev) = prev_move {
+            let entry = &mut self.continuation[piece_index(!turn, prev.role())][prev.to() as usize]
+                [mv.role() as usize - 1][mv.to() as usize];
+            apply_gravity(entry, bonus);
+        }
+    }
+}
+
+/// History gravity: the closer an entry is to the limit, the less a bonus moves it.
+#[inline(always)]
+fn apply_gravity(entry: &mut i16, bonus: i32) {
+    let value = *entry as i32;
+    *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
+}
+
+#[inline(always)]
+fn piece_index(color: Color, role: Role) -> usize {
+    color as usize * 6 + role as usize - 1
+}
+
+#[inline(always)]
+fn from_index(mv: &Move) -> usize {
+    mv.from().map_or(0, |sq| sq as usize)
 }
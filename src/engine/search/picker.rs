@@ -0,0 +1,258 @@
+use shakmaty::{Chess, Color, Move, MoveList, Position, Role};
+
+use crate::engine::search::ordering::MoveOrdering;
+use crate::engine::see::see_ge;
+
+const PROMOTION_BONUS: i32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    Generate,
+    GoodCaptures,
+    Killers,
+    Countermove,
+    ScoreQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the moves of a position one at a time, best guess first.
+///
+/// The TT move is tried before anything is generated, so a TT cutoff costs no
+/// move generation at all. After that come captures that don't lose material by SEE,
+/// the killers and countermove, the remaining quiets by history and finally the
+/// losing captures. Everything lives on the stack; moves are selected, not sorted.
+pub struct MovePicker {
+    stage: Stage,
+    captures_only: bool,
+    turn: Color,
+    prev_move: Option<Move>,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    killer_index: usize,
+    countermove: Option<Move>,
+    captures: MoveList,
+    capture_scores: [i32; 256],
+    // captures[..bad_captures_end] have been set aside as losing
+    bad_captures_end: usize,
+    quiets: MoveList,
+    quiet_scores: [i32; 256],
+    index: usize,
+}
+
+impl MovePicker {
+    pub fn new(
+        pos: &Chess,
+        ordering: &MoveOrdering,
+        ply: usize,
+        prev_move: Option<Move>,
+        tt_move: Option<Move>,
+    ) -> Self {
+        let turn = pos.turn();
+
+        Self {
+            stage: Stage::TtMove,
+            captures_only: false,
+            turn,
+            prev_move,
+            tt_move,
+            killers: ordering.killers(ply),
+            killer_index: 0,
+            countermove: prev_move.and_then(|prev| ordering.countermove(turn, &prev)),
+            captures: MoveList::new(),
+            capture_scores: [0; 256],
+            bad_captures_end: 0,
+            quiets: MoveList::new(),
+            quiet_scores: [0; 256],
+            index: 0,
+        }
+    }
+
+    /// Winning captures and queen promotions only, for quiescence search.
+    /// Losing captures are never handed out.
+    pub fn captures(pos: &Chess) -> Self {
+        Self {
+            stage: Stage::Generate,
+            captures_only: true,
+            turn: pos.turn(),
+            prev_move: None,
+            tt_move: None,
+            killers: [None; 2],
+            killer_index: 0,
+            countermove: None,
+            captures: MoveList::new(),
+            capture_scores: [0; 256],
+            bad_captures_end: 0,
+            quiets: MoveList::new(),
+            quiet_scores: [0; 256],
+            index: 0,
+        }
+    }
+
+    pub fn next(&mut self, pos: &Chess, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::Generate;
+
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::Generate => {
+                    self.generate(pos, ordering);
+                    self.index = 0;
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    while self.index < self.captures.len() {
+                        let mv = self.select_capture();
+
+                        if Some(mv) == self.tt_move {
+                            continue;
+                        }
+
+                        if !is_good_tactical(pos, &mv) {
+                            self.captures.swap(self.bad_captures_end, self.index - 1);
+                            self.bad_captures_end += 1;
+                            continue;
+                        }
+
+                        return Some(mv);
+                    }
+
+                    self.stage = if self.captures_only { Stage::Done } else { Stage::Killers };
+                    self.index = 0;
+                }
+                Stage::Killers => {
+                    while self.killer_index < 2 {
+                        let killer = self.killers[self.killer_index];
+                        self.killer_index += 1;
+
+                        if let Some(mv) = killer {
+                            if Some(mv) != self.tt_move && self.quiets.contains(&mv) {
+                                return Some(mv);
+                            }
+                        }
+                    }
+
+                    self.stage = Stage::Countermove;
+                }
+                Stage::Countermove => {
+                    self.stage = Stage::ScoreQuiets;
+
+                    if let Some(mv) = self.countermove {
+                        if !self.is_special(&mv) && self.quiets.contains(&mv) {
+                            return Some(mv);
+                        }
+                    }
+                }
+                Stage::ScoreQuiets => {
+                    let prev_move = self.prev_move;
+
+                    for (i, mv) in self.quiets.iter().enumerate() {
+                        self.quiet_scores[i] = ordering.quiet_score(self.turn, prev_move.as_ref(), mv);
+                    }
+
+                    self.index = 0;
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    while self.index < self.quiets.len() {
+                        let mv = self.select_quiet();
+
+                        if self.is_special(&mv) || Some(mv) == self.countermove {
+                            continue;
+                        }
+
+                        return Some(mv);
+                    }
+
+                    self.index = 0;
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if self.index < self.bad_captures_end {
+                        self.index += 1;
+                        return Some(self.captures[self.index - 1]);
+                    }
+
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Splits the legal moves into tactical moves (captures and promotions)
+    /// and quiets, scoring the former by MVV-LVA.
+    fn generate(&mut self, pos: &Chess, ordering: &MoveOrdering) {
+        for mv in pos.legal_moves() {
+            if mv.is_capture() || mv.is_promotion() {
+                let mut score = if mv.is_capture() { ordering.mvv_lva_score(pos, &mv) } else { 0 };
+
+                if mv.promotion() == Some(Role::Queen) {
+                    score += PROMOTION_BONUS;
+                }
+
+                self.capture_scores[self.captures.len()] = score;
+                self.captures.push(mv);
+            } else if !self.captures_only {
+                self.quiets.push(mv);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn is_special(&self, mv: &Move) -> bool {
+        Some(*mv) == self.tt_move || self.killers.contains(&Some(*mv))
+    }
+
+    /// Swaps the best remaining capture into place and returns it.
+    #[inline(always)]
+    fn select_capture(&mut self) -> Move {
+        let best = best_index(&self.capture_scores[self.index..self.captures.len()]) + self.index;
+
+        self.captures.swap(self.index, best);
+        self.capture_scores.swap(self.index, best);
+        self.index += 1;
+
+        self.captures[self.index - 1]
+    }
+
+    #[inline(always)]
+    fn select_quiet(&mut self) -> Move {
+        let best = best_index(&self.quiet_scores[self.index..self.quiets.len()]) + self.index;
+
+        self.quiets.swap(self.index, best);
+        self.quiet_scores.swap(self.index, best);
+        self.index += 1;
+
+        self.quiets[self.index - 1]
+    }
+}
+
+#[inline(always)]
+fn best_index(scores: &[i32]) -> usize {
+    let mut best = 0;
+
+    for (i, &score) in scores.iter().enumerate() {
+        if score > scores[best] {
+            best = i;
+        }
+    }
+
+    best
+}
+
+/// Captures are good when they don't lose material by SEE, promotions when
+/// they are to a queen.
+#[inline(always)]
+fn is_good_tactical(pos: &Chess, mv: &Move) -> bool {
+    match mv.promotion() {
+        Some(role) => role == Role::Queen,
+        None => see_ge(pos, mv, 0),
+    }
+}
@@ -1,5 +1,8 @@
+use std::cmp::Reverse;
 use shakmaty::Move;
 
+use crate::engine::types::Score;
+
 #[derive(Clone)]
 pub struct PvTable {
     pub table: Vec<Vec<Move>>, // [ply][line]
@@ -27,13 +30,18 @@ impl PvTable {
         self.table[0].first().cloned()
     }
 
+    /// The reply the best move expects, to ponder on.
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.table[0].get(1).cloned()
+    }
+
     pub fn pv_line(&self) -> &[Move] {
         &self.table[0]
     }
 }
 
 pub struct MultiPv {
-    pub lines: std::vec::Vec<(f32, std::vec::Vec<shakmaty::Move>)>,
+    pub lines: std::vec::Vec<(Score, std::vec::Vec<shakmaty::Move>)>,
     capacity: usize,
 }
 
@@ -49,12 +57,10 @@ impl MultiPv {
         self.lines.clear();
     }
 
-    pub fn insert(&mut self, score: f32, line: Vec<shakmaty::Move>) {
+    pub fn insert(&mut self, score: Score, line: Vec<shakmaty::Move>) {
         self.lines.push((score, line));
 
-        self.lines.sort_by(|a, b| {
-            b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        self.lines.sort_by_key(|line| Reverse(line.0));
 
         self.lines.truncate(self.capacity);
     }
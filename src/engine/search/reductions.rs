@@ -0,0 +1,29 @@
+use crate::engine::params::Params;
+
+const TABLE_SIZE: usize = 64;
+
+/// Late move reductions, precomputed as `base + ln(depth) * ln(move_index) / divisor`.
+pub struct ReductionTable {
+    table: [[u8; TABLE_SIZE]; TABLE_SIZE],
+}
+
+impl ReductionTable {
+    pub fn new(params: &Params) -> Self {
+        let mut table = [[0; TABLE_SIZE]; TABLE_SIZE];
+
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (move_index, r) in row.iter_mut().enumerate().skip(1) {
+                let reduction = params.lmr_base
+                    + (depth as f32).ln() * (move_index as f32).ln() / params.lmr_divisor;
+                *r = reduction.max(0.0) as u8;
+            }
+        }
+
+        Self { table }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, depth: usize, move_index: usize) -> usize {
+        self.table[depth.min(TABLE_SIZE - 1)][move_index.min(TABLE_SIZE - 1)] as usize
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+use shakmaty::Move;
+
+use crate::engine::tt::Bound;
+use crate::engine::types::Score;
+
+/// Snapshot of a completed iteration, handed to the reporter.
+pub struct IterationReport<'a> {
+    pub depth: usize,
+    pub seldepth: u32,
+    pub lines: &'a [(Score, Vec<Move>)],
+    /// `Lower` / `Upper` when the iteration failed out of its aspiration window.
+    pub bound: Bound,
+    pub nodes: u64,
+    pub tbhits: u64,
+    pub elapsed: Duration,
+    pub hashfull: u32,
+}
+
+/// Hook for live search output. Every method defaults to a no-op.
+pub trait SearchReporter {
+    fn iteration(&self, _report: &IterationReport) {}
+
+    fn current_move(&self, _depth: usize, _mv: &Move, _number: usize, _elapsed: Duration) {}
+}
+
+pub struct SilentReporter;
+
+impl SearchReporter for SilentReporter {}
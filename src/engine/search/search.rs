@@ -1,18 +1,29 @@
 use std::cmp;
 use std::time::{Duration, Instant};
-use shakmaty::{Chess, EnPassantMode, Move, Position};
-use shakmaty::zobrist::{Zobrist64};
-use crate::engine::eval::evaluate;
+use shakmaty::zobrist::Zobrist64;
+use shakmaty::{Chess, EnPassantMode, Move, MoveList, Position};
 
 use crate::engine::search::context::SearchContext;
-
-use crate::engine::time_manager::compute_time_limit;
-use crate::engine::tt::Bound;
-use crate::engine::types::{DRAW_SCORE, MATE_SCORE};
+use crate::engine::search::limits::SearchLimits;
+use crate::engine::search::picker::MovePicker;
+use crate::engine::search::report::IterationReport;
+use crate::engine::search::threads::skips_depth;
+use crate::engine::see::see_ge;
+use crate::engine::tablebase::Outcome;
+use crate::engine::tt::{unpack_move, Bound};
+use crate::engine::types::{Score, DRAW_SCORE, INFINITE_SCORE, MAX_PLY, TB_WIN_SCORE};
+
+const ASPIRATION_MIN_DEPTH: usize = 4;
+const NULL_MOVE_MIN_DEPTH: usize = 3;
+const LMR_MIN_DEPTH: usize = 3;
+const SEE_PRUNE_MAX_DEPTH: usize = 8;
+// Tablebase results are better than any search of the same depth
+const TB_STORE_DEPTH_BONUS: usize = 6;
 
 
 pub struct SearchStats {
     pub nodes: u64,
+    pub tbhits: u64,
     pub depth_sum: u64,
     pub depth_samples: u64,
     pub seldepth: u32,
@@ -23,6 +34,7 @@ impl SearchStats {
     pub(crate) fn default() -> SearchStats {
         Self {
             nodes: 0,
+            tbhits: 0,
             depth_sum: 0,
             depth_samples: 0,
             seldepth: 0,
@@ -31,42 +43,155 @@ impl SearchStats {
     }
 }
 
-pub fn search(pos: &Chess, ctx: &mut SearchContext, max_depth: usize, time_remaining: Option<Duration>) -> f32 {
+pub fn search(pos: &Chess, ctx: &mut SearchContext, limits: &SearchLimits) -> Score {
 
     let start = Instant::now();
-    let total_time = compute_time_limit(pos, time_remaining, Some(Duration::from_millis(0)));
 
-    let mut best_score = f32::NEG_INFINITY;
+    let mut best_score = -INFINITE_SCORE;
+    let mut completed_pv = Vec::new();
+    let mut completed_lines = Vec::new();
+
+    ctx.stopped = false;
+    ctx.completed_depth = 0;
+    ctx.limits = limits.clone();
+    ctx.start = start;
 
+    ctx.board.set_root(pos);
 
-    for depth in 1..=max_depth {
-        if start.elapsed() > total_time {
+    // One slot per ply below the root, children are written in place
+    let mut line = vec![Chess::default(); MAX_PLY].into_boxed_slice();
+
+    for depth in 1..=limits.max_depth {
+        if ctx.out_of_time() {
             break;
         }
 
-        ctx.pv.clear_from(0);
-        ctx.multipv.clear();
+        if skips_depth(ctx.thread_id, depth) {
+            continue;
+        }
+
+        // Aspiration window around the previous score, widened on every fail
+        let mut delta = ctx.params.aspiration_window;
+        let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH && ctx.completed_depth > 0 && !best_score.is_mate() {
+            (best_score - Score(delta), best_score + Score(delta))
+        } else {
+            (-INFINITE_SCORE, INFINITE_SCORE)
+        };
+
+        let score = loop {
+            ctx.pv.clear_from(0);
+            ctx.multipv.clear();
+
+            let score = negamax(pos, &mut line, ctx, depth, 0, alpha, beta, true);
+
+            if ctx.stopped {
+                break score;
+            }
+
+            if score <= alpha {
+                report_iteration(ctx, depth, &ctx.multipv.lines, Bound::Upper);
+                beta = Score((alpha.0 + beta.0) / 2);
+                alpha = cmp::max(score - Score(delta), -INFINITE_SCORE);
+            } else if score >= beta {
+                report_iteration(ctx, depth, &ctx.multipv.lines, Bound::Lower);
+                beta = cmp::min(score + Score(delta), INFINITE_SCORE);
+            } else {
+                break score;
+            }
+
+            delta += delta / 2;
+        };
+
+        if ctx.stopped {
+            break;
+        }
 
-        let score = negamax(pos, ctx, depth, 0, f32::NEG_INFINITY, f32::INFINITY);
         best_score = score;
+        ctx.completed_depth = depth;
+        completed_pv = ctx.pv.pv_line().to_vec();
+        completed_lines = ctx.multipv.lines.clone();
+
+        report_iteration(ctx, depth, &completed_lines, Bound::Exact);
+
+        if let Some(moves) = limits.mate {
+            if score.mate_moves().is_some_and(|m| m > 0 && m <= moves as i32) {
+                break;
+            }
+        }
     }
 
+    // An interrupted iteration leaves a partial tree behind, so report the last complete one.
+    if completed_pv.is_empty() {
+        completed_pv.extend(fallback_move(pos, ctx));
+    }
+
+    ctx.pv.table[0] = completed_pv;
+    ctx.multipv.lines = completed_lines;
+
     ctx.stats.duration = start.elapsed();
 
     best_score
 
 }
 
+/// A move to play when `stop` or the node limit came before the first iteration
+/// finished: the best one it had found, or else the first legal move allowed.
+fn fallback_move(pos: &Chess, ctx: &SearchContext) -> Option<Move> {
+    if let Some(&mv) = ctx.pv.pv_line().first() {
+        return Some(mv);
+    }
+
+    let searchmoves = &ctx.limits.searchmoves;
+
+    pos.legal_moves()
+        .into_iter()
+        .find(|mv| searchmoves.is_empty() || searchmoves.contains(mv))
+}
+
+/// The reply to ponder on after a search of `pos`: the second pv move or,
+/// when a TT or tablebase cutoff left the pv one move long, the table's move
+/// for the position after the best move, if it is legal there.
+pub fn ponder_move(pos: &Chess, ctx: &SearchContext) -> Option<Move> {
+    let best_move = ctx.pv.best_move()?;
+
+    if let Some(reply) = ctx.pv.ponder_move() {
+        return Some(reply);
+    }
+
+    let mut child = pos.clone();
+    child.play_unchecked(best_move);
+
+    let key = child.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+
+    ctx.tt.probe(key).and_then(|entry| unpack_move(&child, entry.best_move))
+}
+
+fn report_iteration(ctx: &SearchContext, depth: usize, lines: &[(Score, Vec<Move>)], bound: Bound) {
+    ctx.reporter.iteration(&IterationReport {
+        depth,
+        seldepth: ctx.stats.seldepth,
+        lines,
+        bound,
+        nodes: ctx.total_nodes(),
+        tbhits: ctx.total_tbhits(),
+        elapsed: ctx.start.elapsed(),
+        hashfull: ctx.tt.hashfull(),
+    });
+}
+
 
 #[inline(always)]
+#[allow(clippy::too_many_arguments)]
 fn negamax(
     pos: &Chess,
+    line: &mut [Chess],
     ctx: &mut SearchContext,
     mut depth: usize,
     ply: usize,
-    mut alpha: f32,
-    beta: f32,
-) -> f32 {
+    mut alpha: Score,
+    beta: Score,
+    allow_null: bool,
+) -> Score {
     ctx.stats.nodes += 1;
     ctx.stats.seldepth = cmp::max(ply as u32, ctx.stats.seldepth);
     ctx.stats.depth_sum += ply as u64;
@@ -74,12 +199,15 @@ fn negamax(
 
     ctx.pv.clear_from(ply);
 
-    if pos.is_checkmate() {
-        return -MATE_SCORE + ply as f32;
+    if ctx.should_stop() {
+        return Score::ZERO;
+    }
 
+    if pos.is_checkmate() {
+        return Score::mated_in(ply);
     }
 
-    if ctx.is_threefold(pos) || ctx.is_50_moves(pos){
+    if ctx.board.is_threefold(pos) || ctx.is_50_moves(pos){
         return DRAW_SCORE;
     }
 
@@ -87,50 +215,135 @@ fn negamax(
         return DRAW_SCORE;
     }
 
-    if pos.is_check() {
+    let pv_node = beta.0 - alpha.0 > 1;
+    let in_check = pos.is_check();
+
+    if in_check {
         depth += 1;
     }
 
-    if depth == 0 {
-        return quiescence(pos, ctx, alpha, beta);
+    if depth == 0 || ply >= MAX_PLY - 1 {
+        return quiescence(pos, line, ctx, ply, alpha, beta);
     }
 
-    let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+    let hash = ctx.board.hash();
 
-    if let Some(score) = tt_probe(hash, ctx, depth, alpha, beta) {
-        return score;
+    // No cutoffs at the root, the iteration has to leave a pv behind
+    if ply > 0 {
+        if let Some(score) = tt_probe(hash, ctx, depth, ply, alpha, beta) {
+            return score;
+        }
+
+        if let Some(score) = tb_probe(hash, pos, ctx, depth, ply, alpha, beta) {
+            return score;
+        }
+    }
+
+    // Null move pruning: if passing still fails high, a real move will too.
+    // Not in check, at pv nodes or with only pawns left, where zugzwang is common.
+    if allow_null
+        && !pv_node
+        && !in_check
+        && ply > 0
+        && depth >= NULL_MOVE_MIN_DEPTH
+        && ctx.board.non_pawn_material(pos.turn()) > 0
+    {
+        let static_eval = ctx.evaluate(pos);
+
+        if static_eval >= beta {
+            if let Some(score) = null_move_search(pos, line, ctx, depth, ply, beta, static_eval) {
+                return score;
+            }
+        }
     }
 
     let original_alpha = alpha;
 
-    let mut best_score = f32::NEG_INFINITY;
+    let mut best_score = -INFINITE_SCORE;
     let mut best_move = None;
 
-    let mut moves = pos.legal_moves();
+    let tt_move = tt_best_move(hash, pos, ctx);
+    let prev_move = ctx.board.last_move();
+
+    let mut picker = MovePicker::new(pos, &ctx.ordering, ply, prev_move, tt_move);
+    let mut quiets_tried = MoveList::new();
+    let mut i = 0;
+
+    while let Some(mv) = picker.next(pos, &ctx.ordering) {
+        if ply == 0 && !ctx.limits.searchmoves.is_empty() && !ctx.limits.searchmoves.contains(&mv) {
+            continue;
+        }
+
+        if ply == 0 {
+            ctx.reporter.current_move(ctx.completed_depth + 1, &mv, i + 1, ctx.start.elapsed());
+        }
+
+        let is_quiet = !mv.is_capture() && !mv.is_promotion();
+
+        // Quiets that hang material at low depth are not worth a look
+        if !pv_node
+            && !in_check
+            && i > 0
+            && is_quiet
+            && depth <= SEE_PRUNE_MAX_DEPTH
+            && !best_score.is_mate()
+            && !see_ge(pos, &mv, -ctx.params.see_quiet_margin * (depth * depth) as i32)
+        {
+            continue;
+        }
 
-    // is this correct?
-    let pv_table = &ctx.pv.table;
-    let pv_move: Option<Move> = pv_table.get(ply).and_then(|l| l.first()).cloned();
+        let (child_pos, line) = line.split_first_mut().expect("a slot per ply");
+        ctx.board.make_move(pos, mv, child_pos);
 
-    let tt_move = tt_best_move(hash, ctx);
+        // Late quiet moves are unlikely to be best, search them shallower first
+        let mut reduction = 0;
 
-    ctx.ordering.order_moves(pos, pv_move.as_ref(), tt_move.as_ref(), &mut moves);
+        if depth >= LMR_MIN_DEPTH
+            && i > pv_node as usize
+            && !in_check
+            && is_quiet
+        {
+            let mut r = ctx.reductions.get(depth, i) as i32;
 
+            if pv_node {
+                r -= 1;
+            }
 
-    for mv in moves {
-        let mut child_pos = pos.clone();
+            if child_pos.is_check() {
+                r -= 1;
+            }
+
+            if ctx.ordering.is_killer(ply, &mv) {
+                r -= 1;
+            }
 
-        child_pos.play_unchecked(mv);
+            r -= ctx.ordering.history_score(pos.turn(), &mv) / 8192;
 
+            reduction = r.clamp(0, depth as i32 - 2) as usize;
+        }
 
-        let hash_child = child_pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+        // PVS: only the first move gets the full window, the rest have to
+        // prove they beat alpha before being searched properly
+        let score = if i == 0 {
+            -negamax(child_pos, line, ctx, depth - 1, ply + 1, -beta, -alpha, true)
+        } else {
+            let mut score = -negamax(child_pos, line, ctx, depth - 1 - reduction, ply + 1, -alpha - Score(1), -alpha, true);
 
-        ctx.increase_history(hash_child);
+            if reduction > 0 && score > alpha {
+                score = -negamax(child_pos, line, ctx, depth - 1, ply + 1, -alpha - Score(1), -alpha, true);
+            }
 
+            if score > alpha && score < beta {
+                score = -negamax(child_pos, line, ctx, depth - 1, ply + 1, -beta, -alpha, true);
+            }
+            score
+        };
 
-        let score = -negamax(&child_pos, ctx, depth - 1, ply + 1, -beta, -alpha);
+        ctx.board.unmake_move();
 
-        ctx.decrease_history();
+        if ctx.stopped {
+            return Score::ZERO;
+        }
 
         if score > best_score {
             best_score = score;
@@ -138,41 +351,108 @@ fn negamax(
             update_pv(ply, mv, best_score, ctx);
         }
 
+        if is_quiet {
+            quiets_tried.push(mv);
+        }
+
         if best_score >= beta {
+            if is_quiet {
+                ctx.ordering.update_quiet_stats(pos.turn(), ply, prev_move.as_ref(), &mv, &quiets_tried, depth);
+            }
             break;
         }
 
         if best_score > alpha {
             alpha = best_score;
         }
+
+        i += 1;
     }
-    tt_store(hash, ctx, depth, best_score, original_alpha, beta, best_move,
-    );
+    tt_store(hash, ctx, depth, ply, best_score, bound_for(best_score, original_alpha, beta), best_move);
     best_score
 }
 
+/// Searches the position with the side to move passing. Returns the score to
+/// cut with, or `None` when the null move doesn't fail high (or fails verification).
+fn null_move_search(
+    pos: &Chess,
+    line: &mut [Chess],
+    ctx: &mut SearchContext,
+    depth: usize,
+    ply: usize,
+    beta: Score,
+    static_eval: Score,
+) -> Option<Score> {
+    let params = ctx.params;
+
+    // Adaptive R: deeper and more clearly winning nodes get reduced harder
+    let reduction = params.null_move_reduction
+        + depth / params.null_move_depth_divisor
+        + cmp::min((static_eval - beta).0 / params.null_move_eval_divisor, 3) as usize;
+    let null_depth = depth.saturating_sub(reduction + 1);
+
+    let (null_pos, below) = line.split_first_mut().expect("a slot per ply");
+
+    if !ctx.board.make_null_move(pos, null_pos) {
+        return None;
+    }
+
+    let score = -negamax(null_pos, below, ctx, null_depth, ply + 1, -beta, -beta + Score(1), false);
+
+    ctx.board.unmake_null_move();
+
+    if ctx.stopped || score < beta {
+        return None;
+    }
+
+    // Unproven mates from a null move aren't trustworthy
+    let score = if score.is_mate() { beta } else { score };
+
+    if depth < params.null_move_verify_depth {
+        return Some(score);
+    }
+
+    // At high depth, confirm with a reduced search of our own that may not pass again
+    let verified = negamax(pos, line, ctx, null_depth, ply, beta - Score(1), beta, false);
+
+    if ctx.stopped || verified < beta {
+        return None;
+    }
+
+    Some(score)
+}
+
 #[inline(always)]
 fn quiescence(
     pos: &Chess,
+    line: &mut [Chess],
     ctx: &mut SearchContext,
-    mut alpha: f32,
-    beta: f32,
-) -> f32 {
+    ply: usize,
+    mut alpha: Score,
+    beta: Score,
+) -> Score {
     ctx.stats.nodes += 1;
 
+    if ctx.should_stop() {
+        return Score::ZERO;
+    }
 
-    if ctx.is_threefold(pos) || ctx.is_50_moves(pos){
+    if ctx.board.is_threefold(pos) || ctx.is_50_moves(pos){
         return DRAW_SCORE;
     }
 
-    let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+    let hash = ctx.board.hash();
 
     // TT probe for qsearch
-    if let Some(score) = tt_probe(hash, ctx, 0, alpha, beta) {
+    if let Some(score) = tt_probe(hash, ctx, 0, ply, alpha, beta) {
         return score;
     }
 
-    let stand_pat = evaluate(pos, ctx.params);
+    let stand_pat = ctx.evaluate(pos);
+
+    if ply >= MAX_PLY - 1 {
+        return stand_pat;
+    }
 
     if stand_pat >= beta {
         return beta;
@@ -184,22 +464,19 @@ fn quiescence(
 
     let original_alpha = alpha;
 
-    let mut moves = pos.capture_moves();
-
-    ctx.ordering.order_captures(pos, &mut moves);
-
-    for mv in moves {
-        let mut child = pos.clone();
+    let mut picker = MovePicker::captures(pos);
 
-        child.play_unchecked(mv);
+    while let Some(mv) = picker.next(pos, &ctx.ordering) {
+        let (child, line) = line.split_first_mut().expect("a slot per ply");
+        ctx.board.make_move(pos, mv, child);
 
-        let child_hash = child.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+        let score = -quiescence(child, line, ctx, ply + 1, -beta, -alpha);
 
-        ctx.increase_history(child_hash);
+        ctx.board.unmake_move();
 
-        let score = -quiescence(&child, ctx, -beta, -alpha);
-
-        ctx.decrease_history();
+        if ctx.stopped {
+            return Score::ZERO;
+        }
 
         if score >= beta {
             return beta;
@@ -209,14 +486,13 @@ fn quiescence(
             alpha = score;
         }
     }
-    tt_store(hash, ctx, 0, alpha, original_alpha, beta, None,
-    );
+    tt_store(hash, ctx, 0, ply, alpha, bound_for(alpha, original_alpha, beta), None);
     alpha
 }
 
 
 #[inline(always)]
-fn update_pv(ply: usize, mv: Move, best_score: f32, ctx: &mut SearchContext) {
+fn update_pv(ply: usize, mv: Move, best_score: Score, ctx: &mut SearchContext) {
     let child_line = ctx.pv.table[ply + 1].clone();
     ctx.pv.set_pv(ply, mv, &child_line);
 
@@ -226,18 +502,19 @@ fn update_pv(ply: usize, mv: Move, best_score: f32, ctx: &mut SearchContext) {
     }
 }
 #[inline(always)]
-fn tt_probe(key : u64, ctx: &mut SearchContext, depth: usize, alpha: f32, beta: f32, ) -> Option<f32> {
+fn tt_probe(key : u64, ctx: &mut SearchContext, depth: usize, ply: usize, alpha: Score, beta: Score, ) -> Option<Score> {
 
 
     if let Some(entry) = ctx.tt.probe(key) {
         if entry.depth as usize >= depth {
+            let score = Score::from_tt(entry.score, ply);
             match entry.bound {
-                Bound::Exact => return Some(entry.score),
-                Bound::Lower if entry.score >= beta => {
-                    return Some(entry.score)
+                Bound::Exact => return Some(score),
+                Bound::Lower if score >= beta => {
+                    return Some(score)
                 }
-                Bound::Upper if entry.score <= alpha => {
-                    return Some(entry.score)
+                Bound::Upper if score <= alpha => {
+                    return Some(score)
                 }
                 _ => {}
             }
@@ -245,20 +522,105 @@ fn tt_probe(key : u64, ctx: &mut SearchContext, depth: usize, alpha: f32, beta:
     }
     None
 }
+/// Tablebase WDL probe. A win is only a lower bound and a loss an upper one,
+/// a shorter mate may still be found, so those cut only outside the window.
 #[inline(always)]
-fn tt_store(key : u64, ctx: &mut SearchContext, depth: usize, best_score: f32, alpha: f32, beta: f32, best_move: Option<Move>, ) {
-    let bound = if best_score <= alpha {
+fn tb_probe(key: u64, pos: &Chess, ctx: &mut SearchContext, depth: usize, ply: usize, alpha: Score, beta: Score) -> Option<Score> {
+    let tablebase = ctx.tablebase.as_ref()?;
+
+    if pos.board().occupied().count() == tablebase.max_pieces() && depth < ctx.tb_probe_depth {
+        return None;
+    }
+
+    let outcome = tablebase.probe_wdl(pos)?;
+    ctx.record_tbhit();
+
+    let (score, bound) = match outcome {
+        Outcome::Win => (TB_WIN_SCORE - Score(ply as i32), Bound::Lower),
+        Outcome::Loss => (-TB_WIN_SCORE + Score(ply as i32), Bound::Upper),
+        Outcome::Draw => (DRAW_SCORE, Bound::Exact),
+    };
+
+    let cutoff = match bound {
+        Bound::Exact => true,
+        Bound::Lower => score >= beta,
+        Bound::Upper => score <= alpha,
+    };
+
+    if !cutoff {
+        return None;
+    }
+
+    ctx.tt.store(key, cmp::min(depth + TB_STORE_DEPTH_BONUS, MAX_PLY - 1), score.to_tt(ply), bound, None);
+    Some(score)
+}
+
+#[inline(always)]
+fn tt_store(key : u64, ctx: &mut SearchContext, depth: usize, ply: usize, best_score: Score, bound: Bound, best_move: Option<Move>, ) {
+    ctx.tt.store(key, depth, best_score.to_tt(ply), bound, best_move);
+}
+
+/// What `best_score` tells about a node searched with the window `alpha..beta`.
+#[inline(always)]
+fn bound_for(best_score: Score, alpha: Score, beta: Score) -> Bound {
+    if best_score <= alpha {
         Bound::Upper
     } else if best_score >= beta {
         Bound::Lower
     } else {
         Bound::Exact
-    };
-    ctx.tt.store(key, depth, best_score, bound, best_move);
+    }
 }
 #[inline(always)]
-fn tt_best_move(key : u64, ctx: &mut SearchContext, ) -> Option<Move> {
+fn tt_best_move(key : u64, pos: &Chess, ctx: &mut SearchContext, ) -> Option<Move> {
     ctx.tt
         .probe(key)
-        .and_then(|e| e.best_move.clone())
-}
\ No newline at end of file
+        .and_then(|e| unpack_move(pos, e.best_move))
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::engine::params::Params;
+    use crate::engine::search::threads::search_smp;
+    use crate::engine::tt::TranspositionTable;
+
+    #[test]
+    fn node_limit_counts_every_thread() {
+        let params = Params::default();
+        let tt = TranspositionTable::new(16);
+        let stop = AtomicBool::new(false);
+        let mut ctx = SearchContext::new(&params, 1, &tt, &stop);
+
+        let mut limits = SearchLimits::depth(30);
+        limits.nodes = Some(20_000);
+        search_smp(&Chess::default(), &mut ctx, &limits, 3);
+
+        // Helpers publish their counts every 1024 nodes, so some overshoot is
+        // expected, but nowhere near the limit per thread
+        assert!(ctx.total_nodes() < 2 * 20_000, "{} nodes", ctx.total_nodes());
+    }
+
+    #[test]
+    fn stop_and_node_limit_apply_in_the_first_iteration() {
+        let params = Params::default();
+        let tt = TranspositionTable::new(16);
+        let pos = Chess::default();
+
+        let stop = AtomicBool::new(false);
+        let mut ctx = SearchContext::new(&params, 1, &tt, &stop);
+        let mut limits = SearchLimits::depth(10);
+        limits.nodes = Some(1);
+        search(&pos, &mut ctx, &limits);
+        assert_eq!((ctx.stats.nodes, ctx.completed_depth), (1, 0));
+        assert!(ctx.pv.best_move().is_some());
+
+        // The flag is polled every 1024 nodes
+        let stopped = AtomicBool::new(true);
+        let mut ctx = SearchContext::new(&params, 1, &tt, &stopped);
+        search(&pos, &mut ctx, &SearchLimits::depth(10));
+        assert!(ctx.stats.nodes <= 1024, "{} nodes", ctx.stats.nodes);
+        assert!(ctx.pv.best_move().is_some());
+    }
+}
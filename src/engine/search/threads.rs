@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use shakmaty::Chess;
+
+use crate::engine::search::context::SearchContext;
+use crate::engine::search::limits::SearchLimits;
+use crate::engine::search::search::search;
+use crate::engine::types::Score;
+
+// Deep quiescence lines blow through the default 2 MiB thread stack.
+pub const SEARCH_STACK_SIZE: usize = 32 * 1024 * 1024;
+
+pub const MAX_THREADS: usize = 256;
+
+// Iterations each helper leaves out, cycling with the helper id: helper `i`
+// skips a depth when `(depth + SKIP_PHASE[i]) / SKIP_SIZE[i]` is odd. Helpers
+// then run ahead of the main thread at different depths and fill the table
+// with work it has not done yet, instead of repeating its search.
+const SKIP_SIZE: [usize; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Whether thread `thread_id` leaves out the iteration at `depth`. The main
+/// thread, id 0, searches every depth.
+pub fn skips_depth(thread_id: usize, depth: usize) -> bool {
+    if thread_id == 0 {
+        return false;
+    }
+
+    let i = (thread_id - 1) % SKIP_SIZE.len();
+    (depth + SKIP_PHASE[i]) / SKIP_SIZE[i] % 2 == 1
+}
+
+/// `limits` with the search moves cut down to those the tables rate best,
+/// probed once here for all threads.
+fn tablebase_limits(pos: &Chess, ctx: &mut SearchContext, limits: &SearchLimits) -> SearchLimits {
+    let mut limits = limits.clone();
+
+    if let Some(moves) = ctx.tablebase.as_ref().and_then(|tb| tb.root_moves(pos)) {
+        ctx.record_tbhit();
+
+        let searchmoves = &mut limits.searchmoves;
+
+        if searchmoves.is_empty() {
+            *searchmoves = moves;
+        } else if searchmoves.iter().any(|mv| moves.contains(mv)) {
+            searchmoves.retain(|mv| moves.contains(mv));
+        }
+    }
+
+    limits
+}
+
+/// Lazy SMP: `threads - 1` helpers search the same root, each skipping its own
+/// set of depths, and only talk to the main thread through the shared
+/// transposition table. `ctx` is the main
+/// thread; its result alone decides the best move and the info output.
+/// Afterwards `ctx.total_nodes()` and `ctx.total_tbhits()` still include what
+/// the helpers reported.
+pub fn search_smp(pos: &Chess, ctx: &mut SearchContext, limits: &SearchLimits, threads: usize) -> Score {
+    ctx.tt.new_search();
+
+    let limits = &tablebase_limits(pos, ctx, limits);
+
+    if threads <= 1 {
+        return search(pos, ctx, limits);
+    }
+
+    let params = ctx.params;
+    let tt = ctx.tt;
+    let history = ctx.board.history().to_vec();
+    let network = ctx.board.network().cloned();
+    let tablebase = ctx.tablebase.clone();
+    let tb_probe_depth = ctx.tb_probe_depth;
+
+    // Helpers run until the main thread is done, whatever stopped it.
+    let helpers_stop = AtomicBool::new(false);
+    let helper_nodes = Arc::new(AtomicU64::new(0));
+    let helper_tbhits = Arc::new(AtomicU64::new(0));
+
+    let mut helper_limits = SearchLimits::depth(limits.max_depth);
+    helper_limits.searchmoves = limits.searchmoves.clone();
+
+    thread::scope(|s| {
+        for id in 1..threads {
+            let history = history.clone();
+            let helpers_stop = &helpers_stop;
+            let helper_nodes = Arc::clone(&helper_nodes);
+            let helper_tbhits = Arc::clone(&helper_tbhits);
+            let helper_limits = &helper_limits;
+            let network = network.clone();
+            let tablebase = tablebase.clone();
+
+            thread::Builder::new()
+                .name(format!("helper-{}", id))
+                .stack_size(SEARCH_STACK_SIZE)
+                .spawn_scoped(s, move || {
+                    let mut helper = SearchContext::new(params, 1, tt, helpers_stop);
+                    helper.board.set_history(history);
+                    helper.board.set_network(network);
+                    helper.thread_id = id;
+                    helper.shared_nodes = Some(helper_nodes);
+                    helper.shared_tbhits = Some(helper_tbhits);
+                    helper.tablebase = tablebase;
+                    helper.tb_probe_depth = tb_probe_depth;
+
+                    search(pos, &mut helper, helper_limits);
+                })
+                .expect("failed to spawn helper thread");
+        }
+
+        ctx.shared_nodes = Some(Arc::clone(&helper_nodes));
+        ctx.shared_tbhits = Some(Arc::clone(&helper_tbhits));
+
+        let score = search(pos, ctx, limits);
+
+        helpers_stop.store(true, Ordering::Relaxed);
+
+        score
+    })
+}
@@ -0,0 +1,137 @@
+use shakmaty::attacks::{bishop_attacks, rook_attacks};
+use shakmaty::{Bitboard, Chess, Move, Position, Role};
+
+/// Piece values used by the exchange evaluation, P, N, B, R, Q, K.
+pub const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+#[inline(always)]
+pub(crate) fn value(role: Role) -> i32 {
+    SEE_VALUES[role as usize - 1]
+}
+
+/// Static exchange evaluation: does `mv` win at least `threshold` once all
+/// captures on its target square have been played out, each side always
+/// recapturing with its least valuable piece?
+///
+/// Sliders lined up behind a capturing piece join in as it leaves (x-rays).
+/// Pins are ignored. Castling, en passant and promotions count as an even
+/// exchange.
+pub fn see_ge(pos: &Chess, mv: &Move, threshold: i32) -> bool {
+    let (from, to) = match *mv {
+        Move::Normal { from, to, promotion: None, .. } => (from, to),
+        _ => return 0 >= threshold,
+    };
+
+    let board = pos.board();
+
+    let mut swap = mv.capture().map_or(0, value) - threshold;
+    if swap < 0 {
+        return false;
+    }
+
+    swap = value(mv.role()) - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    let mut occupied = board.occupied() ^ Bitboard::from(from) ^ Bitboard::from(to);
+    let mut side = pos.turn();
+    let mut attackers = board.attacks_to(to, side, occupied) | board.attacks_to(to, !side, occupied);
+
+    let diagonal = board.bishops_and_queens();
+    let straight = board.rooks_and_queens();
+
+    // 1 while the side that made the first capture is ahead
+    let mut result = 1;
+
+    loop {
+        side = !side;
+        attackers &= occupied;
+
+        let own_attackers = attackers & board.by_color(side);
+        if own_attackers.is_empty() {
+            break;
+        }
+
+        result ^= 1;
+
+        let Some(role) = [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen, Role::King]
+            .into_iter()
+            .find(|&role| (own_attackers & board.by_role(role)).any())
+        else {
+            break;
+        };
+
+        if role == Role::King {
+            // Capturing with the king is only legal if nothing recaptures
+            if (attackers & !board.by_color(side)).any() {
+                result ^= 1;
+            }
+            break;
+        }
+
+        swap = value(role) - swap;
+        if swap < result {
+            break;
+        }
+
+        occupied ^= (own_attackers & board.by_role(role)).isolate_first();
+
+        // Uncover x-rays behind the piece that just captured
+        if matches!(role, Role::Pawn | Role::Bishop | Role::Queen) {
+            attackers |= bishop_attacks(to, occupied) & diagonal;
+        }
+        if matches!(role, Role::Rook | Role::Queen) {
+            attackers |= rook_attacks(to, occupied) & straight;
+        }
+    }
+
+    result != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::utility::read_position_from_fen;
+    use crate::uci::parser::uci_to_move;
+
+    /// The exact gain of `mv` for the side to move in `fen`: the highest
+    /// threshold it still passes.
+    fn assert_gain(fen: &str, mv: &str, gain: i32) {
+        let pos = read_position_from_fen(fen).unwrap();
+        let mv = uci_to_move(&pos, mv);
+
+        assert!(see_ge(&pos, &mv, gain), "{} in {} should win {}", mv, fen, gain);
+        assert!(!see_ge(&pos, &mv, gain + 1), "{} in {} should win only {}", mv, fen, gain);
+    }
+
+    #[test]
+    fn undefended_pawn() {
+        assert_gain("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100);
+    }
+
+    #[test]
+    fn queen_takes_defended_pawn() {
+        assert_gain("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", "d1d5", 100 - 900);
+    }
+
+    #[test]
+    fn knight_takes_pawn_defended_by_pawn() {
+        assert_gain("4k3/2p5/3p4/8/4N3/8/8/4K3 w - - 0 1", "e4d6", 100 - 320);
+    }
+
+    #[test]
+    fn rook_behind_rook_recaptures() {
+        assert_gain("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100);
+    }
+
+    #[test]
+    fn king_cannot_recapture_a_defended_piece() {
+        assert_gain("8/8/8/3pk3/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100);
+    }
+
+    #[test]
+    fn quiet_move_onto_an_attacked_square() {
+        assert_gain("4k3/8/2p5/8/8/8/8/3RK3 w - - 0 1", "d1d5", -500);
+    }
+}
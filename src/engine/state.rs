@@ -1,12 +1,27 @@
-use shakmaty::{Chess, EnPassantMode, Position};
+use std::sync::Arc;
+use shakmaty::{Chess, EnPassantMode, Move, Position};
 use shakmaty::zobrist::{Zobrist64};
 
+use crate::engine::book::{Book, BookSelection, DEFAULT_BOOK_DEPTH};
+use crate::engine::nnue::Network;
+use crate::engine::tablebase::{Tablebase, DEFAULT_PROBE_DEPTH};
 use crate::engine::tt::TranspositionTable;
 
 pub struct EngineState {
     pub position: Chess,
     pub repetition_stack: Vec<u64>,
-    pub tt: TranspositionTable,
+    pub tt: Arc<TranspositionTable>,
+    /// Net used for evaluation, `None` for the handcrafted eval.
+    pub network: Option<Arc<Network>>,
+    /// Syzygy tables from `SyzygyPath`, if any.
+    pub tablebase: Option<Arc<Tablebase>>,
+    pub syzygy_probe_depth: usize,
+    /// Polyglot book from `BookFile`, played from while `own_book` is set.
+    pub book: Option<Book>,
+    pub own_book: bool,
+    /// Last full move number the book is consulted for.
+    pub book_depth: u32,
+    pub book_selection: BookSelection,
 }
 
 impl EngineState {
@@ -22,10 +37,32 @@ impl EngineState {
         Self {
             position,
             repetition_stack,
-            tt: TranspositionTable::new(tt_size),
+            tt: Arc::new(TranspositionTable::new(tt_size)),
+            network: Network::embedded(),
+            tablebase: None,
+            syzygy_probe_depth: DEFAULT_PROBE_DEPTH,
+            book: None,
+            own_book: false,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_selection: BookSelection::Weighted,
         }
     }
 
+    /// A book move for the current position, if the book is on and the game
+    /// is still within `book_depth` moves.
+    pub fn book_move(&self) -> Option<Move> {
+        if !self.own_book || self.position.fullmoves().get() > self.book_depth {
+            return None;
+        }
+
+        self.book.as_ref()?.pick(&self.position, self.book_selection)
+    }
+
+    /// Swaps in a freshly allocated table. Callers stop the search first.
+    pub fn resize_tt(&mut self, size_mb: usize) {
+        self.tt = Arc::new(TranspositionTable::new(size_mb));
+    }
+
     pub fn init_history(&mut self) {
         self.repetition_stack.clear();
 
@@ -0,0 +1,144 @@
+use std::io;
+
+use shakmaty::{Chess, Move};
+#[cfg(feature = "syzygy")]
+use shakmaty::Position;
+#[cfg(feature = "syzygy")]
+use shakmaty_syzygy::{Tablebase as Tables, Wdl};
+
+pub const DEFAULT_PROBE_DEPTH: usize = 1;
+pub const MAX_PROBE_DEPTH: usize = 100;
+
+/// Whether tablebase support is compiled in, see the `syzygy` feature.
+pub const HAS_SYZYGY: bool = cfg!(feature = "syzygy");
+
+/// A tablebase result for the side to move, with the 50-move rule applied.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "syzygy"), allow(dead_code))]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+/// Syzygy endgame tablebases, shared by all search threads.
+pub struct Tablebase {
+    #[cfg(feature = "syzygy")]
+    tables: Tables<Chess>,
+    files: usize,
+}
+
+impl Tablebase {
+    /// Number of table files found.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+}
+
+#[cfg(not(feature = "syzygy"))]
+impl Tablebase {
+    pub fn load(_paths: &str) -> io::Result<Tablebase> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "built without the syzygy feature"))
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        0
+    }
+
+    pub fn probe_wdl(&self, _pos: &Chess) -> Option<Outcome> {
+        None
+    }
+
+    pub fn root_moves(&self, _pos: &Chess) -> Option<Vec<Move>> {
+        None
+    }
+}
+
+#[cfg(feature = "syzygy")]
+impl Tablebase {
+    /// Loads every table found in `paths`, directories separated by `;` or,
+    /// outside Windows, `:` as well.
+    pub fn load(paths: &str) -> io::Result<Tablebase> {
+        let separators: &[char] = if cfg!(windows) { &[';'] } else { &[';', ':'] };
+        let mut tables = Tables::new();
+        let mut files = 0;
+
+        for path in paths.split(separators).map(str::trim).filter(|p| !p.is_empty()) {
+            files += tables.add_directory(path)?;
+        }
+
+        Ok(Tablebase { tables, files })
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+
+    /// Whether `pos` is small enough to be in the tables. Positions with
+    /// castling rights never are.
+    #[inline(always)]
+    pub fn covers(&self, pos: &Chess) -> bool {
+        pos.board().occupied().count() <= self.max_pieces() && pos.castles().is_empty()
+    }
+
+    /// Win, draw or loss for the side to move, right after a capture or pawn
+    /// move. Only then is the WDL table exact, later in the 50-move count a
+    /// win may already have turned into a draw. Wins and losses that the
+    /// 50-move rule spoils count as draws.
+    #[inline(always)]
+    pub fn probe_wdl(&self, pos: &Chess) -> Option<Outcome> {
+        if pos.halfmoves() != 0 || !self.covers(pos) {
+            return None;
+        }
+
+        match self.tables.probe_wdl_after_zeroing(pos).ok()? {
+            Wdl::Win => Some(Outcome::Win),
+            Wdl::Loss => Some(Outcome::Loss),
+            Wdl::CursedWin | Wdl::BlessedLoss | Wdl::Draw => Some(Outcome::Draw),
+        }
+    }
+
+    /// The root moves that keep the best result the tables allow, taking the
+    /// 50-move counter into account, and among those the fastest wins or the
+    /// slowest losses by DTZ. `None` when the root is not covered or a table
+    /// is missing, in which case search considers every move.
+    pub fn root_moves(&self, pos: &Chess) -> Option<Vec<Move>> {
+        if !self.covers(pos) {
+            return None;
+        }
+
+        let mut ranked = Vec::new();
+
+        for mv in pos.legal_moves() {
+            let mut child = pos.clone();
+            child.play_unchecked(mv);
+            ranked.push((mv, self.result_after(&child)?));
+        }
+
+        let best = ranked.iter().map(|&(_, rank)| rank).max()?;
+
+        Some(ranked.into_iter().filter(|&(_, rank)| rank == best).map(|(mv, _)| mv).collect())
+    }
+
+    /// Result of the move that led to `child`, for the side that played it,
+    /// with a tiebreak that is higher the sooner a win zeroes the 50-move
+    /// counter, or the later a loss does.
+    fn result_after(&self, child: &Chess) -> Option<(Wdl, i32)> {
+        if child.is_checkmate() {
+            return Some((Wdl::Win, i32::MAX));
+        }
+
+        // DTZ counts plies to the next zeroing move; the win only holds if that
+        // comes before the 50-move rule does
+        let dtz = self.tables.probe_dtz(child).ok()?.ignore_rounding().0;
+        let within_50 = dtz.abs() + child.halfmoves() as i32 <= 100;
+
+        Some(match dtz {
+            0 => (Wdl::Draw, 0),
+            d if d > 0 && within_50 => (Wdl::Loss, d),
+            d if d > 0 => (Wdl::BlessedLoss, d),
+            d if within_50 => (Wdl::Win, d),
+            d => (Wdl::CursedWin, d),
+        })
+    }
+}
@@ -6,6 +6,7 @@ pub fn compute_time_limit(
     pos: &impl Position,
     remaining: Option<Duration>,
     increment: Option<Duration>,
+    movestogo: Option<u32>,
 ) -> Duration {
     let remaining = match remaining {
         Some(t) => t,
@@ -15,7 +16,8 @@ pub fn compute_time_limit(
     let increment = increment.unwrap_or(Duration::ZERO);
 
     // --- Base allocation ---
-    let mut time = remaining / 10 + increment;
+    let moves_left = movestogo.unwrap_or(10).clamp(1, 50);
+    let mut time = remaining / moves_left + increment;
 
     // --- Complexity adjustment ---
     let move_count = pos.legal_moves().len() as u32;
@@ -1,54 +1,122 @@
-use shakmaty::Move;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use shakmaty::{CastlingMode, Chess, Move, Role, Square};
+use shakmaty::uci::UciMove;
 
-#[derive(Clone, Copy)]
+use crate::engine::types::Score;
+
+pub const DEFAULT_HASH_MB: usize = 128;
+pub const MAX_HASH_MB: usize = 65536;
+
+const BUCKET_SIZE: usize = 8;
+const GENERATION_MASK: u8 = 63;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Bound {
     Exact,
     Lower, // beta cutoff
     Upper, // alpha cutoff
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct TTEntry {
-    pub key: u64,
     pub depth: u8,
-    pub score: f32,
+    pub score: Score,
     pub bound: Bound,
-    pub best_move: Option<Move>,
+    pub best_move: u16,
+    generation: u8,
+    key: u16,
+}
+
+impl TTEntry {
+    // layout: move [0..16) | score [16..32) | depth [32..40) | bound [40..42)
+    //         | generation [42..48) | key [48..64)
+    // Bounds start at 1 so an occupied entry never packs to zero.
+    #[inline(always)]
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1u64,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+
+        self.best_move as u64
+            | ((self.score.0 as i16 as u16 as u64) << 16)
+            | ((self.depth as u64) << 32)
+            | (bound << 40)
+            | ((self.generation as u64) << 42)
+            | ((self.key as u64) << 48)
+    }
+
+    #[inline(always)]
+    fn unpack(data: u64) -> Self {
+        let bound = match (data >> 40) & 3 {
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => Bound::Exact,
+        };
+
+        Self {
+            depth: (data >> 32) as u8,
+            score: Score((data >> 16) as u16 as i16 as i32),
+            bound,
+            best_move: data as u16,
+            generation: (data >> 42) as u8 & GENERATION_MASK,
+            key: (data >> 48) as u16,
+        }
+    }
 }
-#[derive(Clone)]
+
+/// Eight packed entries sharing one cache line. Every entry is a single atomic
+/// word, so threads can read and write without locks and never see a torn entry.
+#[repr(align(64))]
+struct Bucket {
+    entries: [AtomicU64; BUCKET_SIZE],
+}
+
 pub struct TranspositionTable {
-    table: Vec<Option<TTEntry>>,
-    mask: usize,
-    entries : usize,
+    buckets: Vec<Bucket>,
+    generation: AtomicU8,
 }
 
 impl TranspositionTable {
     pub fn new(size_mb: usize) -> Self {
-        let bytes = size_mb * 1024 * 1024;
-        let entry_size = size_of::<Option<TTEntry>>();
-        let mut capacity = bytes / entry_size;
-
-        // force power of two
-        capacity = capacity.next_power_of_two();
+        let bytes = size_mb.max(1) * 1024 * 1024;
+        let capacity = (bytes / size_of::<Bucket>()).max(1);
 
         Self {
-            table: vec![None; capacity],
-            mask: capacity - 1,
-            entries : 0
+            buckets: (0..capacity)
+                .map(|_| Bucket { entries: std::array::from_fn(|_| AtomicU64::new(0)) })
+                .collect(),
+            generation: AtomicU8::new(0),
         }
     }
 
+    /// Maps the high bits of the key onto the bucket range; the low 16 bits
+    /// are what the entry itself keeps for verification.
     #[inline(always)]
-    fn index(&self, key: u64) -> usize {
-        key as usize & self.mask
+    fn bucket(&self, key: u64) -> &Bucket {
+        let idx = ((key as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[idx]
+    }
+
+    /// Ages every entry by one search so stale ones get replaced first.
+    pub fn new_search(&self) {
+        let next = (self.generation.load(Ordering::Relaxed) + 1) & GENERATION_MASK;
+        self.generation.store(next, Ordering::Relaxed);
     }
 
     #[inline(always)]
-    pub fn probe(&self, key: u64) -> Option<&TTEntry> {
-        let entry = &self.table[self.index(key)];
-        if let Some(e) = entry {
-            if e.key == key {
-                return Some(e);
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        let bucket = self.bucket(key);
+
+        for slot in &bucket.entries {
+            let data = slot.load(Ordering::Relaxed);
+
+            if data != 0 {
+                let entry = TTEntry::unpack(data);
+                if entry.key == key as u16 {
+                    return Some(entry);
+                }
             }
         }
         None
@@ -56,49 +124,205 @@ impl TranspositionTable {
 
     #[inline(always)]
     pub fn store(
-        &mut self,
+        &self,
         key: u64,
         depth: usize,
-        score: f32,
+        score: Score,
         bound: Bound,
         best_move: Option<Move>,
     ) {
-        let idx = self.index(key);
+        let bucket = self.bucket(key);
+        let generation = self.generation.load(Ordering::Relaxed);
+        let key16 = key as u16;
+
+        // Same position first, then an empty slot, then the shallowest / oldest one.
+        let mut victim = 0;
+        let mut victim_value = i32::MAX;
+        let mut previous = None;
 
-        if self.table[idx].is_none() {
-            self.entries += 1;
+        for (i, slot) in bucket.entries.iter().enumerate() {
+            let data = slot.load(Ordering::Relaxed);
+
+            if data == 0 {
+                victim = i;
+                break;
+            }
+
+            let entry = TTEntry::unpack(data);
+
+            if entry.key == key16 {
+                victim = i;
+                previous = Some(entry);
+                break;
+            }
+
+            let age = (generation.wrapping_sub(entry.generation) & GENERATION_MASK) as i32;
+            let value = entry.depth as i32 - 8 * age;
+
+            if value < victim_value {
+                victim = i;
+                victim_value = value;
+            }
         }
 
-        if let Some(existing) = &self.table[idx] {
-            if existing.depth > depth as u8 {
-                return; // don't replace deeper entry
+        let mut best_move = best_move.map_or(0, |mv| pack_move(&mv));
+
+        if let Some(existing) = previous {
+            // don't replace a deeper result for this position from the current search
+            if existing.generation == generation
+                && existing.depth as usize > depth
+                && bound != Bound::Exact
+            {
+                return;
+            }
+
+            if best_move == 0 {
+                best_move = existing.best_move;
             }
         }
 
         let entry = TTEntry {
-            key,
             depth: depth as u8,
             score,
             bound,
             best_move,
+            generation,
+            key: key16,
         };
 
-        self.table[idx] = Some(entry);
+        bucket.entries[victim].store(entry.pack(), Ordering::Relaxed);
     }
-    pub fn clear(&mut self) {
-        self.entries = 0;
-        unsafe {
-            std::ptr::write_bytes(
-                self.table.as_mut_ptr(),
-                0,
-                self.table.len(),
-            );
+
+    pub fn clear(&self) {
+        self.generation.store(0, Ordering::Relaxed);
+        for bucket in &self.buckets {
+            for slot in &bucket.entries {
+                slot.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Permille of the first 1000 entries written during the current search.
+    pub fn hashfull(&self) -> u32 {
+        let generation = self.generation.load(Ordering::Relaxed);
+
+        self.buckets
+            .iter()
+            .flat_map(|bucket| bucket.entries.iter())
+            .take(1000)
+            .filter(|slot| {
+                let data = slot.load(Ordering::Relaxed);
+                data != 0 && TTEntry::unpack(data).generation == generation
+            })
+            .count() as u32
+    }
+}
+
+/// Packs a move as from | to << 6 | promotion << 12. Zero means no move.
+#[inline(always)]
+pub fn pack_move(mv: &Move) -> u16 {
+    match mv.to_uci(CastlingMode::Chess960) {
+        UciMove::Normal { from, to, promotion } => {
+            from as u16 | (to as u16) << 6 | (promotion.map_or(0, |r| r as u16)) << 12
         }
+        _ => 0,
+    }
+}
+
+/// Turns a packed move back into a move, if it is legal in `pos`.
+#[inline(always)]
+pub fn unpack_move(pos: &Chess, packed: u16) -> Option<Move> {
+    if packed == 0 {
+        return None;
     }
-    pub fn tt_occupancy(&self) -> u32 {
-        let used = self.entries as f64;
-        let total = self.table.len() as f64;
 
-        ((used / total) * 1000.0) as u32
+    let from = Square::new((packed & 63) as u32);
+    let to = Square::new(((packed >> 6) & 63) as u32);
+    let promotion = Role::try_from((packed >> 12) as u8).ok();
+
+    UciMove::Normal { from, to, promotion }.to_move(pos).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use shakmaty::Position;
+
+    use super::*;
+    use crate::engine::utility::read_position_from_fen;
+    use crate::uci::parser::uci_to_move;
+
+    const KEY: u64 = 0x9d39_247e_3377_6d41;
+
+    #[test]
+    fn probe_returns_what_was_stored() {
+        let tt = TranspositionTable::new(1);
+        let pos = Chess::default();
+        let mv = uci_to_move(&pos, "g1f3");
+
+        assert!(tt.probe(KEY).is_none());
+
+        tt.store(KEY, 7, Score(-123), Bound::Lower, Some(mv));
+        let entry = tt.probe(KEY).unwrap();
+
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.score, Score(-123));
+        assert!(entry.bound == Bound::Lower);
+        assert_eq!(unpack_move(&pos, entry.best_move), Some(mv));
+        assert!(tt.probe(KEY ^ 1).is_none());
+
+        tt.clear();
+        assert!(tt.probe(KEY).is_none());
+    }
+
+    #[test]
+    fn shallower_bound_keeps_a_deeper_entry_from_this_search() {
+        let tt = TranspositionTable::new(1);
+
+        tt.store(KEY, 10, Score(50), Bound::Exact, None);
+        tt.store(KEY, 4, Score(-20), Bound::Upper, None);
+        assert_eq!(tt.probe(KEY).unwrap().depth, 10);
+
+        tt.new_search();
+        tt.store(KEY, 4, Score(-20), Bound::Upper, None);
+        assert_eq!(tt.probe(KEY).unwrap().depth, 4);
+    }
+
+    #[test]
+    fn store_without_a_move_keeps_the_previous_one() {
+        let tt = TranspositionTable::new(1);
+        let pos = Chess::default();
+        let mv = uci_to_move(&pos, "e2e4");
+
+        tt.store(KEY, 3, Score(10), Bound::Lower, Some(mv));
+        tt.store(KEY, 5, Score(20), Bound::Upper, None);
+
+        assert_eq!(unpack_move(&pos, tt.probe(KEY).unwrap().best_move), Some(mv));
+    }
+
+    #[test]
+    fn every_legal_move_survives_packing() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+        ];
+
+        for fen in fens {
+            let pos = read_position_from_fen(fen).unwrap();
+
+            for mv in pos.legal_moves() {
+                let packed = pack_move(&mv);
+                assert_ne!(packed, 0, "{} in {}", mv, fen);
+                assert_eq!(unpack_move(&pos, packed), Some(mv), "{} in {}", mv, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_and_illegal_moves_unpack_to_nothing() {
+        let pos = Chess::default();
+
+        assert_eq!(unpack_move(&pos, 0), None);
+        assert_eq!(unpack_move(&pos, pack_move(&uci_to_move(&pos, "e2e4")) ^ (1 << 6)), None);
     }
 }
@@ -1,2 +1,173 @@
-pub const MATE_SCORE: f32 = 30_000.0;
-pub const DRAW_SCORE: f32 = 0.0;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+pub const MAX_PLY: usize = 128;
+
+pub const MATE_SCORE: Score = Score(30_000);
+pub const DRAW_SCORE: Score = Score(0);
+pub const INFINITE_SCORE: Score = Score(32_000);
+
+// Anything beyond this is a forced mate, no eval comes close.
+const MATE_BOUND: i32 = MATE_SCORE.0 - MAX_PLY as i32;
+
+/// A tablebase win at the root, less a ply for every move it takes to get there.
+pub const TB_WIN_SCORE: Score = Score(MATE_BOUND - 1 - MAX_PLY as i32);
+
+// Tablebase wins and mates alike are relative to the ply they were found at.
+const DECISIVE_BOUND: i32 = TB_WIN_SCORE.0 - MAX_PLY as i32;
+
+/// Centipawn score from the side to move's point of view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(pub i32);
+
+impl Score {
+    pub const ZERO: Score = Score(0);
+
+    #[inline(always)]
+    pub fn from_cp(cp: f32) -> Self {
+        Score(cp.round() as i32)
+    }
+
+    /// Score for being mated `ply` half-moves from the root.
+    #[inline(always)]
+    pub const fn mated_in(ply: usize) -> Self {
+        Score(-MATE_SCORE.0 + ply as i32)
+    }
+
+    #[inline(always)]
+    pub fn is_mate(self) -> bool {
+        self.0.abs() >= MATE_BOUND
+    }
+
+    /// Full moves until mate, negative when we are the one getting mated.
+    pub fn mate_moves(self) -> Option<i32> {
+        if !self.is_mate() {
+            return None;
+        }
+
+        if self.0 > 0 {
+            Some((MATE_SCORE.0 - self.0 + 1) / 2)
+        } else {
+            Some(-(MATE_SCORE.0 + self.0) / 2)
+        }
+    }
+
+    /// Mate and tablebase scores are stored relative to the node instead of
+    /// the root, so they stay valid when the entry is hit at another ply.
+    #[inline(always)]
+    pub fn to_tt(self, ply: usize) -> Self {
+        if self.0 >= DECISIVE_BOUND {
+            Score(self.0 + ply as i32)
+        } else if self.0 <= -DECISIVE_BOUND {
+            Score(self.0 - ply as i32)
+        } else {
+            self
+        }
+    }
+
+    #[inline(always)]
+    pub fn from_tt(score: Score, ply: usize) -> Self {
+        if score.0 >= DECISIVE_BOUND {
+            Score(score.0 - ply as i32)
+        } else if score.0 <= -DECISIVE_BOUND {
+            Score(score.0 + ply as i32)
+        } else {
+            score
+        }
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn add(self, rhs: Score) -> Score {
+        Score(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn sub(self, rhs: Score) -> Score {
+        Score(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Score {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Score) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Score {
+    #[inline(always)]
+    fn sub_assign(&mut self, rhs: Score) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    #[inline(always)]
+    fn neg(self) -> Score {
+        Score(-self.0)
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_scores_count_full_moves() {
+        assert_eq!((-Score::mated_in(1)).mate_moves(), Some(1));
+        assert_eq!((-Score::mated_in(3)).mate_moves(), Some(2));
+        assert_eq!((-Score::mated_in(4)).mate_moves(), Some(2));
+        assert_eq!(Score::mated_in(2).mate_moves(), Some(-1));
+        assert_eq!(Score::mated_in(0).mate_moves(), Some(0));
+    }
+
+    #[test]
+    fn evals_and_tablebase_wins_are_not_mates() {
+        assert!(!Score(1_500).is_mate());
+        assert!(!TB_WIN_SCORE.is_mate());
+        assert!(!(-TB_WIN_SCORE).is_mate());
+        assert_eq!(Score(1_500).mate_moves(), None);
+        assert!((-Score::mated_in(MAX_PLY)).is_mate());
+    }
+
+    #[test]
+    fn decisive_scores_are_stored_relative_to_the_node() {
+        // Mate found 7 plies from the root at a node 5 plies deep
+        let mate = -Score::mated_in(7);
+        let stored = mate.to_tt(5);
+        assert_eq!(stored, -Score::mated_in(2));
+        assert_eq!(Score::from_tt(stored, 5), mate);
+
+        // The same node reached at ply 9 is 11 plies from mate
+        assert_eq!(Score::from_tt(stored, 9), -Score::mated_in(11));
+        assert_eq!(Score::from_tt(Score::mated_in(7).to_tt(5), 9), Score::mated_in(11));
+
+        let tb_win = TB_WIN_SCORE - Score(6);
+        assert_eq!(Score::from_tt(tb_win.to_tt(6), 2), TB_WIN_SCORE - Score(2));
+        assert_eq!(Score::from_tt((-tb_win).to_tt(6), 2), -TB_WIN_SCORE + Score(2));
+    }
+
+    #[test]
+    fn evals_are_stored_as_is() {
+        for score in [Score::ZERO, Score(250), Score(-1_200)] {
+            assert_eq!(score.to_tt(17), score);
+            assert_eq!(Score::from_tt(score, 3), score);
+        }
+    }
+}
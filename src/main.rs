@@ -1,53 +1,109 @@
 mod engine;
+mod tune;
 mod uci;
 
-use std::cmp;
+use std::env;
 use std::io::{self, BufRead};
-use std::sync::atomic::Ordering;
-use std::time::Duration;
-
-use shakmaty::{perft, Chess, Color, EnPassantMode, Position};
-use shakmaty::zobrist::Zobrist64;
-use crate::uci::{parser::*, state::*};
-use crate::engine::search::search::{search, SearchStats};
-use crate::engine::params::Params;
-use crate::engine::search::ordering::MoveOrdering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::process;
+use std::time::{Duration, Instant};
+
+use shakmaty::{Chess, Color, Position};
+use crate::uci::{parser::*, report::{bestmove_to_uci, score_to_uci, UciReporter}, state::*};
+use crate::engine::search::search::{ponder_move, search};
+use crate::engine::search::limits::SearchLimits;
+use crate::engine::search::bench::{bench, DEFAULT_BENCH_DEPTH, DEFAULT_BENCH_HASH_MB};
+use crate::engine::search::threads::{search_smp, MAX_THREADS};
+use crate::engine::perft::{divide as perft_divide, nps, perft, run_suite};
+use crate::engine::params::{Params, EXPOSE_SPIN_OPTIONS};
 use crate::engine::search::context::*;
-use crate::engine::search::pv::{MultiPv, PvTable};
 use crate::engine::time_manager::compute_time_limit;
 use crate::engine::state::*;
+use crate::engine::tt::{DEFAULT_HASH_MB, MAX_HASH_MB};
+use crate::engine::book::{Book, BookSelection, DEFAULT_BOOK_DEPTH, MAX_BOOK_DEPTH};
+use crate::engine::nnue::{Network, EMBEDDED_NET_NAME, HAS_EMBEDDED_NET};
+use crate::engine::tablebase::{Tablebase, DEFAULT_PROBE_DEPTH, HAS_SYZYGY, MAX_PROBE_DEPTH};
 use crate::engine::utility::read_position_from_fen;
 
 fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    let mut params = Params::default();
+
+    if let Some(i) = args.iter().position(|arg| arg == "--params") {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("--params needs a file");
+            return;
+        };
+
+        params = match Params::load(path) {
+            Ok(params) => params,
+            Err(e) => {
+                eprintln!("failed to load params from {}: {}", path, e);
+                return;
+            }
+        };
+
+        args.drain(i..i + 2);
+    }
+
+    match args.get(1).map(String::as_str) {
+        Some("tune") => {
+            tune::run(&args[2..], params);
+            return;
+        }
+        Some("spsa") => {
+            print_spsa_inputs(&params);
+            return;
+        }
+        Some("perftsuite") => {
+            if let UciCommand::PerftSuite { path, max_depth } = parse_command(&args[1..].join(" ")) {
+                match run_suite(&path, max_depth, false) {
+                    Ok(0) => {}
+                    Ok(_) => process::exit(1),
+                    Err(e) => {
+                        eprintln!("failed to read {}: {}", path, e);
+                        process::exit(1);
+                    }
+                }
+            }
+            return;
+        }
+        Some("bench") => {
+            if let UciCommand::Bench { depth, hash, threads } = parse_command(&args[1..].join(" ")) {
+                run_bench(&params, Network::embedded(), depth, hash, threads);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     let debug = false;
 
     if debug {
         let fen = "2k4r/1r1q2pp/QBp2p2/1p6/8/8/P4PPP/2R3K1 w - - 1 0";
         let pos = read_position_from_fen(fen).unwrap();
 
-        let params = Params::default();
-        let max_depth = 50;
-        let time_remaining = Duration::from_millis(10000);
+        let mut limits = SearchLimits::depth(50);
+        limits.time_limit = Some(Duration::from_millis(10000));
         let multipv = 3;
 
-        let ordering = MoveOrdering::new(&params.piece_values);
-
-        let engine_state = EngineState::new(128);
-        let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
-        let mut tt = engine_state.tt;
+        let engine_state = EngineState::new(DEFAULT_HASH_MB);
+        let stop = AtomicBool::new(false);
 
-        let mut ctx = SearchContext::new(&params, &ordering,multipv,&mut tt,hash);
+        let mut ctx = SearchContext::new(&params,multipv,&engine_state.tt,&stop);
 
-        let score = search(&pos, &mut ctx, max_depth, Some(time_remaining));
+        let score = search(&pos, &mut ctx, &limits);
 
         let best_move = ctx.pv.best_move().unwrap();
         let stats = ctx.stats;
         let multipv_lines = ctx.multipv;
-        let tt_occupancy = ctx.tt.tt_occupancy();
+        let tt_occupancy = ctx.tt.hashfull();
 
 
         println!("Best move: {:?}", move_to_uci(&best_move));
-        println!("Score: {:.2}", score);
+        println!("Score: {}", score_to_uci(score));
         println!("Time taken: {:?}", stats.duration);
         println!("Nodes searched: {}", stats.nodes);
         println!("NPS: {:.0}", stats.nodes as f64 / stats.duration.as_secs_f64());
@@ -57,7 +113,7 @@ fn main() {
         println!("\nMultiPV:");
         let multi_pv_lines = &multipv_lines.lines;
         for (i, (score, line)) in multi_pv_lines.iter().enumerate() {
-            print!("{}: score {:.2} pv", i + 1, score);
+            print!("{}: score {} pv", i + 1, score_to_uci(*score));
             for mv in line {
                 print!(" {}", move_to_uci(mv));
             }
@@ -66,8 +122,7 @@ fn main() {
     } else {
         let stdin = io::stdin();
         let mut uci_state = UciState::new();
-        let mut engine_state = EngineState::new(128);
-        let params = Params::default();
+        let mut engine_state = EngineState::new(DEFAULT_HASH_MB);
 
         for line in stdin.lock().lines() {
             let line = line.unwrap();
@@ -77,13 +132,46 @@ fn main() {
                 UciCommand::Uci => {
                     println!("id name FastPeaPea");
                     println!("id author Warre G.");
+                    println!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_MB, MAX_HASH_MB);
                     println!("option name MultiPV type spin default 1 min 1 max 5");
+                    println!("option name Ponder type check default false");
+                    println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                    println!(
+                        "option name EvalFile type string default {}",
+                        if HAS_EMBEDDED_NET { EMBEDDED_NET_NAME } else { "<empty>" }
+                    );
+                    if HAS_SYZYGY {
+                        println!("option name SyzygyPath type string default <empty>");
+                        println!(
+                            "option name SyzygyProbeDepth type spin default {} min 1 max {}",
+                            DEFAULT_PROBE_DEPTH, MAX_PROBE_DEPTH
+                        );
+                    }
+                    println!("option name OwnBook type check default false");
+                    println!("option name BookFile type string default <empty>");
+                    println!(
+                        "option name BookDepth type spin default {} min 1 max {}",
+                        DEFAULT_BOOK_DEPTH, MAX_BOOK_DEPTH
+                    );
+                    println!("option name BookBestMove type check default false");
+                    if EXPOSE_SPIN_OPTIONS {
+                        for option in params.spin_options() {
+                            println!(
+                                "option name {} type spin default {} min {} max {}",
+                                option.name,
+                                option.value,
+                                option.min,
+                                option.max
+                            );
+                        }
+                    }
                     println!("uciok");
                 }
 
                 UciCommand::IsReady => println!("readyok"),
 
                 UciCommand::UciNewGame => {
+                    uci_state.stop_search();
                     uci_state.position = Chess::new();
                     engine_state.position = Chess::new();
                     engine_state.tt.clear();
@@ -112,14 +200,22 @@ fn main() {
                     binc,
                     movetime,
                     depth,
+                    nodes,
+                    mate,
+                    movestogo,
+                    infinite,
+                    ponder,
+                    searchmoves,
                 } => {
-                    uci_state.stop.store(false, Ordering::Relaxed);
-
-                    let max_depth = if let Some(d) = depth {
-                        d as usize
-                    } else {
-                        64
-                    };
+                    // Analysis always searches, games play from the book while it lasts.
+                    // A pondering search must wait for `ponderhit`, so it searches too.
+                    if !infinite && !ponder && searchmoves.is_empty() {
+                        if let Some(mv) = engine_state.book_move() {
+                            uci_state.stop_search();
+                            println!("bestmove {}", move_to_uci(&mv));
+                            continue;
+                        }
+                    }
 
                     let remaining = match engine_state.position.turn() {
                         Color::White => wtime.map(Duration::from_millis),
@@ -131,119 +227,258 @@ fn main() {
                         Color::Black => binc.map(Duration::from_millis),
                     };
 
-                    let time_limit = if let Some(ms) = movetime {
-                        Some(Duration::from_millis(ms))
-                    } else {
-                        if max_depth != 64 {
-                            Some(Duration::MAX / 10)
-                        } else {
-                            Some(compute_time_limit(
-                                &engine_state.position,
-                                remaining,
-                                increment,
-                            ))
-                        }
-                    };
-
-                    let ordering = MoveOrdering::new(&params.piece_values);
-                    let repetition_stack = &engine_state.repetition_stack;
-                    let hash = engine_state.position.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
-
-                    let mut ctx = SearchContext {
-                        params: &params,
-                        ordering: &ordering,
-                        pv: PvTable::new(64),
-                        stats: SearchStats::default(),
-                        multipv: MultiPv::new(uci_state.multipv),
-                        repetition_stack: repetition_stack.to_vec(),
-                        tt: &mut engine_state.tt,
-                        hash
+                    let mut limits = match depth {
+                        Some(d) => SearchLimits::depth(d as usize),
+                        None => SearchLimits::new(),
                     };
-
-                    let _score = search(
-                        &engine_state.position,
-                        &mut ctx,
-                        max_depth,
-                        time_limit,
-                    );
-
-                    let stats = ctx.stats;
-                    let multipv_lines = ctx.multipv;
-                    let best_move = ctx.pv.best_move().unwrap();
-                    let tt_occupancy = ctx.tt.tt_occupancy();
-
-
-                    let elapsed = stats.duration.as_secs_f64();
-                    let elapsed_millis = stats.duration.as_millis();
-
-                    let nps = if elapsed > 0.0 {
-                        (stats.nodes as f64 / elapsed) as u64
+                    limits.nodes = nodes;
+                    limits.mate = mate;
+                    limits.infinite = infinite;
+                    limits.searchmoves = searchmoves
+                        .iter()
+                        .filter_map(|mv| try_uci_to_move(&engine_state.position, mv))
+                        .collect();
+
+                    limits.time_limit = if infinite {
+                        None
+                    } else if let Some(ms) = movetime {
+                        Some(Duration::from_millis(ms))
+                    } else if remaining.is_some() {
+                        Some(compute_time_limit(
+                            &engine_state.position,
+                            remaining,
+                            increment,
+                            movestogo,
+                        ))
+                    } else if depth.is_some() || nodes.is_some() || mate.is_some() {
+                        None
                     } else {
-                        0
+                        Some(compute_time_limit(&engine_state.position, None, None, None))
                     };
-                    let multi_pv_lines = &multipv_lines.lines;
-                    for (i, (score, line)) in
-                        multi_pv_lines.iter().enumerate()
-                    {
-                        let pv_string = pv_to_string(line);
 
-                        println!(
-                            "info depth {:.0} seldepth {} multipv {} score cp {} nodes {} nps {} hashfull {} time {} pv {}",
-                            line.len(),
-                            stats.seldepth,
-                            i + 1,
-                            score,
-                            stats.nodes,
-                            nps,
-                            tt_occupancy,
-                            elapsed_millis,
-                            pv_string
-                        );
-                    }
+                    let position = engine_state.position.clone();
+                    let repetition_stack = engine_state.repetition_stack.clone();
+                    let tt = Arc::clone(&engine_state.tt);
+                    let network = engine_state.network.clone();
+                    let tablebase = engine_state.tablebase.clone();
+                    let tb_probe_depth = engine_state.syzygy_probe_depth;
+                    let stop = Arc::clone(&uci_state.stop);
+                    let pondering = Arc::clone(&uci_state.pondering);
+                    let params = params.clone();
+                    let multipv = uci_state.multipv;
+                    let threads = uci_state.threads;
+
+                    uci_state.pondering.store(ponder, Ordering::Relaxed);
+
+                    uci_state.start_search(move || {
+                        let mut ctx = SearchContext::new(&params, multipv, &tt, &stop);
+                        ctx.pondering = Some(&pondering);
+                        ctx.board.set_history(repetition_stack);
+                        ctx.board.set_network(network);
+                        ctx.tablebase = tablebase;
+                        ctx.tb_probe_depth = tb_probe_depth;
+                        ctx.reporter = &UciReporter;
+
+                        let _score = search_smp(&position, &mut ctx, &limits, threads);
+
+                        // `go infinite` may only answer once the GUI sends `stop`, a
+                        // pondering search once it sends `stop` or `ponderhit`
+                        while (limits.infinite || pondering.load(Ordering::Relaxed)) && !stop.load(Ordering::Relaxed) {
+                            thread::sleep(Duration::from_millis(1));
+                        }
 
-                    println!("bestmove {}", move_to_uci(&best_move));
+                        println!("{}", bestmove_to_uci(ctx.pv.best_move(), ponder_move(&position, &ctx)));
+                    });
                 }
 
                 UciCommand::SetOption { name, value } => {
                     if name.as_str().eq_ignore_ascii_case("multipv") {
                         if let Ok(n) = value.as_str().parse::<usize>() {
                             println!("{}", n);
-                            uci_state.multipv = cmp::min(cmp::max(n,1),5);
+                            uci_state.multipv = n.clamp(1, 5);
                         }
+                    } else if name.as_str().eq_ignore_ascii_case("hash") {
+                        if let Ok(n) = value.as_str().parse::<usize>() {
+                            uci_state.stop_search();
+                            engine_state.resize_tt(n.clamp(1, MAX_HASH_MB));
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("threads") {
+                        if let Ok(n) = value.as_str().parse::<usize>() {
+                            uci_state.threads = n.clamp(1, MAX_THREADS);
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("evalfile") {
+                        uci_state.stop_search();
+
+                        if value.is_empty() || value == "<empty>" {
+                            engine_state.network = None;
+                            println!("info string using the handcrafted evaluation");
+                        } else if value == EMBEDDED_NET_NAME {
+                            engine_state.network = Network::embedded();
+                            match engine_state.network {
+                                Some(_) => println!("info string using the embedded net"),
+                                None => println!("info string no embedded net, using the handcrafted evaluation"),
+                            }
+                        } else {
+                            match Network::load(&value) {
+                                Ok(network) => {
+                                    engine_state.network = Some(Arc::new(network));
+                                    println!("info string loaded EvalFile {}", value);
+                                }
+                                Err(e) => println!("info string failed to load EvalFile {}: {}", value, e),
+                            }
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("syzygypath") {
+                        uci_state.stop_search();
+                        engine_state.tablebase = None;
+
+                        if !value.is_empty() && value != "<empty>" {
+                            match Tablebase::load(&value) {
+                                Ok(tablebase) if tablebase.files() > 0 => {
+                                    println!(
+                                        "info string found {} tablebase files, up to {} pieces",
+                                        tablebase.files(),
+                                        tablebase.max_pieces()
+                                    );
+                                    engine_state.tablebase = Some(Arc::new(tablebase));
+                                }
+                                Ok(_) => println!("info string no tablebase files in {}", value),
+                                Err(e) => println!("info string failed to load SyzygyPath {}: {}", value, e),
+                            }
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("syzygyprobedepth") {
+                        if let Ok(n) = value.as_str().parse::<usize>() {
+                            engine_state.syzygy_probe_depth = n.clamp(1, MAX_PROBE_DEPTH);
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("ownbook") {
+                        engine_state.own_book = value.eq_ignore_ascii_case("true");
+                    } else if name.as_str().eq_ignore_ascii_case("bookfile") {
+                        engine_state.book = None;
+
+                        if !value.is_empty() && value != "<empty>" {
+                            match Book::load(&value) {
+                                Ok(book) => {
+                                    println!("info string loaded BookFile {}, {} entries", value, book.entry_count());
+                                    engine_state.book = Some(book);
+                                }
+                                Err(e) => println!("info string failed to load BookFile {}: {}", value, e),
+                            }
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("bookdepth") {
+                        if let Ok(n) = value.as_str().parse::<u32>() {
+                            engine_state.book_depth = n.clamp(1, MAX_BOOK_DEPTH);
+                        }
+                    } else if name.as_str().eq_ignore_ascii_case("bookbestmove") {
+                        engine_state.book_selection = if value.eq_ignore_ascii_case("true") {
+                            BookSelection::Best
+                        } else {
+                            BookSelection::Weighted
+                        };
+                    } else if let Ok(n) = value.as_str().parse::<i32>() {
+                        // Searches clone `params` at `go`, so a running one is unaffected
+                        params.set_spin_option(&name, n);
                     }
                 }
 
+                // The opponent played the expected move: keep searching, now on our clock
+                UciCommand::PonderHit => uci_state.pondering.store(false, Ordering::Relaxed),
+
                 UciCommand::Stop => {
-                    uci_state.stop.store(true, Ordering::Relaxed);
+                    uci_state.stop_search();
+                }
+
+                UciCommand::Bench { depth, hash, threads } => {
+                    uci_state.stop_search();
+                    run_bench(&params, engine_state.network.clone(), depth, hash, threads);
                 }
 
-                UciCommand::Perft { depth } => {
-                    let start = std::time::Instant::now();
-                    let nodes = perft(&engine_state.position, depth);
-                    let elapsed = start.elapsed().as_millis();
-                    let nps = (1000*nodes as u128 / elapsed) as u64;
+                UciCommand::Perft { depth, divide } => {
+                    uci_state.stop_search();
+                    run_perft(&engine_state.position, depth, divide, uci_state.debug);
+                }
 
-                    println!("nodes {}", nodes);
-                    println!("time {:.3}", elapsed);
-                    println!("nps {}", nps);
-                    println!("perftok");
+                UciCommand::PerftSuite { path, max_depth } => {
+                    uci_state.stop_search();
+                    if let Err(e) = run_suite(&path, max_depth, uci_state.debug) {
+                        println!("info string failed to read {}: {}", path, e);
+                    }
                 }
 
-                UciCommand::Quit => break,
+                UciCommand::Debug(on) => uci_state.debug = on,
+
+                UciCommand::Quit => {
+                    uci_state.stop_search();
+                    break;
+                }
 
                 _ => {}
             }
         }
+
+        // stdin closed mid-search: let it finish so `bestmove` still gets printed
+        uci_state.wait_search();
     }
 }
 
-fn pv_to_string(line: &[shakmaty::Move]) -> String {
-    let mut s = String::new();
-
-    for mv in line {
-        s.push(' ');
-        s.push_str(move_to_uci(mv).as_str());
+/// Prints the spin options in the OpenBench SPSA input format:
+/// `name, int, value, min, max, step, learning rate`.
+fn print_spsa_inputs(params: &Params) {
+    for option in params.spin_options() {
+        println!(
+            "{}, int, {}, {}, {}, {}, 0.002",
+            option.name,
+            option.value,
+            option.min,
+            option.max,
+            option.step
+        );
     }
+}
+
+fn run_bench(
+    params: &Params,
+    network: Option<Arc<Network>>,
+    depth: Option<usize>,
+    hash: Option<usize>,
+    threads: Option<usize>,
+) {
+    let result = bench(
+        params,
+        network,
+        depth.unwrap_or(DEFAULT_BENCH_DEPTH).max(1),
+        hash.unwrap_or(DEFAULT_BENCH_HASH_MB).clamp(1, MAX_HASH_MB),
+        threads.unwrap_or(1).clamp(1, MAX_THREADS),
+    );
+
+    println!("time {} ms", result.elapsed.as_millis());
+    println!("{} nodes {} nps", result.nodes, nps(result.nodes, result.elapsed));
+}
 
-    s
+/// `perft` prints the total, `go perft` the count below each root move as well.
+fn run_perft(pos: &Chess, depth: u32, divide: bool, check_hash: bool) {
+    let start = Instant::now();
+
+    let nodes = if divide {
+        perft_divide(pos, depth, check_hash).map(|moves| {
+            for (mv, nodes) in &moves {
+                println!("{}: {}", move_to_uci(mv), nodes);
+            }
+            println!();
+
+            if depth == 0 { 1 } else { moves.iter().map(|(_, nodes)| nodes).sum() }
+        })
+    } else {
+        perft(pos, depth, check_hash)
+    };
+
+    match nodes {
+        Ok(nodes) => {
+            let elapsed = start.elapsed();
+
+            println!("nodes {}", nodes);
+            println!("time {}", elapsed.as_millis());
+            println!("nps {}", nps(nodes, elapsed));
+            println!("perftok");
+        }
+        Err(e) => println!("info string {}", e),
+    }
 }
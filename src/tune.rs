@@ -0,0 +1,343 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::thread;
+use std::time::Instant;
+
+use shakmaty::{Chess, Position};
+
+use crate::engine::eval::evaluate_white;
+use crate::engine::params::Params;
+use crate::engine::utility::read_position_from_fen;
+
+const DEFAULT_EPOCHS: usize = 1000;
+const DEFAULT_LEARNING_RATE: f32 = 1.0;
+const DEFAULT_OUTPUT: &str = "tuned_params.toml";
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+// Coefficients smaller than this are float noise from the finite difference
+const MIN_COEFFICIENT: f32 = 1e-3;
+
+struct TuneOptions {
+    data: String,
+    epochs: usize,
+    learning_rate: f32,
+    output: String,
+    limit: Option<usize>,
+    threads: usize,
+}
+
+/// One training position reduced to a linear model of the evaluation:
+/// `eval = constant + sum(coefficient * weight)` over the weights it touches.
+struct Trace {
+    result: f32,
+    constant: f32,
+    start: usize,
+    end: usize,
+}
+
+/// Coefficients of all positions, stored back to back.
+#[derive(Default)]
+struct TraceSet {
+    traces: Vec<Trace>,
+    indices: Vec<u32>,
+    coefficients: Vec<f32>,
+}
+
+/// `tune <data> [epochs N] [lr X] [out FILE] [limit N] [threads N]`
+///
+/// Texel tuning of the evaluation weights in `params` against game results, read
+/// from `fen | cp | result` lines as written by `python/dataextractor.py`. The
+/// result is a config file for `--params`, or Rust source if `out` ends in `.rs`.
+pub fn run(args: &[String], mut params: Params) {
+    let Some(options) = parse_options(args) else {
+        eprintln!("usage: tune <data> [epochs N] [lr X] [out FILE] [limit N] [threads N]");
+        return;
+    };
+
+    let positions = match load_positions(&options.data, options.limit) {
+        Ok(positions) => positions,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", options.data, e);
+            return;
+        }
+    };
+
+    if positions.is_empty() {
+        eprintln!("no usable positions in {}", options.data);
+        return;
+    }
+
+    let mut weights = params.tunable_weights();
+
+    println!("{} positions, {} weights, {} threads", positions.len(), weights.len(), options.threads);
+
+    let start = Instant::now();
+    let traces = build_traces(&positions, &params, options.threads);
+    drop(positions);
+
+    println!(
+        "traced in {:.1}s, {:.1} coefficients per position",
+        start.elapsed().as_secs_f32(),
+        traces.coefficients.len() as f32 / traces.traces.len() as f32
+    );
+
+    let k = fit_k(&traces, &weights);
+    println!("K = {:.4}, error {:.6}", k, mean_error(&traces, &weights, k));
+
+    adam(&traces, &mut weights, k, &options);
+
+    // One decimal is well below what the data can resolve
+    let rounded: Vec<f32> = weights.iter().map(|w| (w * 10.0).round() / 10.0).collect();
+    params.set_tunable_weights(&rounded);
+
+    let written = if options.output.ends_with(".rs") {
+        fs::write(&options.output, params.tunable_source())
+    } else {
+        params.save(&options.output)
+    };
+
+    match written {
+        Ok(()) => println!("wrote {}", options.output),
+        Err(e) => eprintln!("failed to write {}: {}", options.output, e),
+    }
+}
+
+fn parse_options(args: &[String]) -> Option<TuneOptions> {
+    let mut options = TuneOptions {
+        data: args.first()?.clone(),
+        epochs: DEFAULT_EPOCHS,
+        learning_rate: DEFAULT_LEARNING_RATE,
+        output: DEFAULT_OUTPUT.to_string(),
+        limit: None,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        let value = args.get(i + 1)?;
+
+        match args[i].as_str() {
+            "epochs" => options.epochs = value.parse().ok()?,
+            "lr" => options.learning_rate = value.parse().ok()?,
+            "out" => options.output = value.clone(),
+            "limit" => options.limit = Some(value.parse().ok()?),
+            "threads" => options.threads = value.parse::<usize>().ok()?.max(1),
+            _ => return None,
+        }
+
+        i += 2;
+    }
+
+    Some(options)
+}
+
+/// Reads `fen | cp | result` lines, result from white's point of view. Positions
+/// in check are skipped, their static eval says little about the outcome.
+fn load_positions(path: &str, limit: Option<usize>) -> io::Result<Vec<(Chess, f32)>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut positions = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut fields = line.split('|').map(str::trim);
+
+        let (Some(fen), Some(_cp), Some(result)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+
+        let (Some(pos), Ok(result)) = (read_position_from_fen(fen), result.parse::<f32>()) else {
+            continue;
+        };
+
+        if pos.is_check() {
+            continue;
+        }
+
+        positions.push((pos, result));
+
+        if limit.is_some_and(|limit| positions.len() >= limit) {
+            break;
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Linearises the evaluation of every position around `params` by nudging each
+/// weight by one and measuring the change.
+fn build_traces(positions: &[(Chess, f32)], params: &Params, threads: usize) -> TraceSet {
+    let chunk_size = positions.len().div_ceil(threads);
+
+    let parts: Vec<TraceSet> = thread::scope(|s| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || trace_chunk(chunk, params)))
+            .collect();
+
+        handles.into_iter().map(|h| h.join().expect("tuner thread panicked")).collect()
+    });
+
+    let mut set = TraceSet::default();
+
+    for part in parts {
+        let offset = set.indices.len();
+
+        set.traces.extend(part.traces.into_iter().map(|t| Trace {
+            start: t.start + offset,
+            end: t.end + offset,
+            ..t
+        }));
+        set.indices.extend(part.indices);
+        set.coefficients.extend(part.coefficients);
+    }
+
+    set
+}
+
+fn trace_chunk(positions: &[(Chess, f32)], params: &Params) -> TraceSet {
+    let mut params = params.clone();
+    let weights = params.tunable_weights();
+    let mut set = TraceSet::default();
+
+    for (pos, result) in positions {
+        let base = evaluate_white(pos, &params, None);
+        let start = set.indices.len();
+        let mut constant = base;
+
+        for (index, &value) in weights.iter().enumerate() {
+            params.tunable_weight_mut(index).set(value + 1.0);
+            let coefficient = evaluate_white(pos, &params, None) - base;
+            params.tunable_weight_mut(index).set(value);
+
+            if coefficient.abs() > MIN_COEFFICIENT {
+                set.indices.push(index as u32);
+                set.coefficients.push(coefficient);
+                constant -= coefficient * value;
+            }
+        }
+
+        set.traces.push(Trace {
+            result: *result,
+            constant,
+            start,
+            end: set.indices.len(),
+        });
+    }
+
+    set
+}
+
+#[inline(always)]
+fn linear_eval(traces: &TraceSet, trace: &Trace, weights: &[f32]) -> f32 {
+    let mut eval = trace.constant;
+
+    for i in trace.start..trace.end {
+        eval += traces.coefficients[i] * weights[traces.indices[i] as usize];
+    }
+
+    eval
+}
+
+#[inline(always)]
+fn sigmoid(eval: f32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+fn mean_error(traces: &TraceSet, weights: &[f32], k: f64) -> f64 {
+    let total: f64 = traces
+        .traces
+        .iter()
+        .map(|trace| {
+            let error = trace.result as f64 - sigmoid(linear_eval(traces, trace, weights), k);
+            error * error
+        })
+        .sum();
+
+    total / traces.traces.len() as f64
+}
+
+/// Golden-section search for the K that maps evals to results best.
+fn fit_k(traces: &TraceSet, weights: &[f32]) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.01, 5.0);
+
+    for _ in 0..40 {
+        let a = high - ratio * (high - low);
+        let b = low + ratio * (high - low);
+
+        if mean_error(traces, weights, a) < mean_error(traces, weights, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// Full-batch Adam on the mean squared error between sigmoid(eval) and result.
+fn adam(traces: &TraceSet, weights: &mut [f32], k: f64, options: &TuneOptions) {
+    let mut m = vec![0.0f64; weights.len()];
+    let mut v = vec![0.0f64; weights.len()];
+    let chunk_size = traces.traces.len().div_ceil(options.threads);
+
+    for epoch in 1..=options.epochs {
+        let gradient = gradient(traces, weights, k, chunk_size);
+
+        for i in 0..weights.len() {
+            m[i] = ADAM_BETA1 * m[i] + (1.0 - ADAM_BETA1) * gradient[i];
+            v[i] = ADAM_BETA2 * v[i] + (1.0 - ADAM_BETA2) * gradient[i] * gradient[i];
+
+            let m_hat = m[i] / (1.0 - ADAM_BETA1.powi(epoch as i32));
+            let v_hat = v[i] / (1.0 - ADAM_BETA2.powi(epoch as i32));
+
+            weights[i] -= (options.learning_rate as f64 * m_hat / (v_hat.sqrt() + ADAM_EPSILON)) as f32;
+        }
+
+        if epoch % 50 == 0 || epoch == options.epochs {
+            println!("epoch {} error {:.6}", epoch, mean_error(traces, weights, k));
+        }
+    }
+}
+
+fn gradient(traces: &TraceSet, weights: &[f32], k: f64, chunk_size: usize) -> Vec<f64> {
+    let scale = k * 10f64.ln() / 400.0;
+    let count = traces.traces.len() as f64;
+
+    let parts: Vec<Vec<f64>> = thread::scope(|s| {
+        let handles: Vec<_> = traces
+            .traces
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut gradient = vec![0.0f64; weights.len()];
+
+                    for trace in chunk {
+                        let predicted = sigmoid(linear_eval(traces, trace, weights), k);
+                        let delta = 2.0 * (predicted - trace.result as f64) * predicted * (1.0 - predicted) * scale / count;
+
+                        for i in trace.start..trace.end {
+                            gradient[traces.indices[i] as usize] += delta * traces.coefficients[i] as f64;
+                        }
+                    }
+
+                    gradient
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().expect("tuner thread panicked")).collect()
+    });
+
+    let mut total = vec![0.0f64; weights.len()];
+    for part in parts {
+        for (sum, value) in total.iter_mut().zip(part) {
+            *sum += value;
+        }
+    }
+
+    total
+}
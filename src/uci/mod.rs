@@ -1,3 +1,3 @@
-
 pub(crate) mod parser;
 pub(crate) mod state;
+pub(crate) mod report;
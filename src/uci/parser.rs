@@ -16,7 +16,15 @@ pub enum UciCommand {
         winc: Option<u64>,
         binc: Option<u64>,
         depth: Option<u32>,
+        nodes: Option<u64>,
+        mate: Option<u32>,
+        movestogo: Option<u32>,
+        infinite: bool,
+        /// Search the expected reply on the opponent's time until `ponderhit` or `stop`.
+        ponder: bool,
+        searchmoves: Vec<String>,
     },
+    PonderHit,
     Stop,
     Quit,
     SetOption {
@@ -25,12 +33,35 @@ pub enum UciCommand {
     },
     Perft {
         depth: u32,
+        divide: bool,
+    },
+    PerftSuite {
+        path: String,
+        max_depth: Option<u32>,
+    },
+    Debug(bool),
+    Bench {
+        depth: Option<usize>,
+        hash: Option<usize>,
+        threads: Option<usize>,
     },
     Unknown,
 }
 
+const GO_KEYWORDS: [&str; 12] = [
+    "searchmoves", "ponder", "wtime", "btime", "winc", "binc",
+    "movestogo", "depth", "nodes", "mate", "movetime", "infinite",
+];
+
+/// Parses the value following the keyword at `tokens[*i]` and steps over it.
+fn parse_value<T: std::str::FromStr>(tokens: &[&str], i: &mut usize) -> Option<T> {
+    let value = tokens.get(*i + 1)?.parse().ok()?;
+    *i += 1;
+    Some(value)
+}
+
 pub fn parse_command(input: &str) -> UciCommand {
-    let tokens: Vec<&str> = input.trim().split_whitespace().collect();
+    let tokens: Vec<&str> = input.split_whitespace().collect();
 
     if tokens.is_empty() {
         return UciCommand::Unknown;
@@ -41,6 +72,7 @@ pub fn parse_command(input: &str) -> UciCommand {
         "isready" => UciCommand::IsReady,
         "ucinewgame" => UciCommand::UciNewGame,
         "stop" => UciCommand::Stop,
+        "ponderhit" => UciCommand::PonderHit,
         "quit" => UciCommand::Quit,
         "position" => {
             let mut fen = None;
@@ -64,6 +96,10 @@ pub fn parse_command(input: &str) -> UciCommand {
 
             UciCommand::Position { fen, moves }
         }
+        "go" if tokens.get(1) == Some(&"perft") => UciCommand::Perft {
+            depth: tokens.get(2).and_then(|d| d.parse().ok()).unwrap_or(1),
+            divide: true,
+        },
         "go" => {
             let mut wtime = None;
             let mut btime = None;
@@ -71,20 +107,38 @@ pub fn parse_command(input: &str) -> UciCommand {
             let mut winc = None;
             let mut binc = None;
             let mut depth = None;
+            let mut nodes = None;
+            let mut mate = None;
+            let mut movestogo = None;
+            let mut infinite = false;
+            let mut ponder = false;
+            let mut searchmoves = Vec::new();
 
             let mut i = 1;
 
-            while i + 1 < tokens.len() {
+            // Flags like `infinite` take no value, so step one token at a time.
+            while i < tokens.len() {
                 match tokens[i] {
-                    "wtime" => wtime = tokens[i + 1].parse().ok(),
-                    "btime" => btime = tokens[i + 1].parse().ok(),
-                    "movetime" => movetime = tokens[i + 1].parse().ok(),
-                    "winc" => winc = tokens[i + 1].parse().ok(),
-                    "binc" => binc = tokens[i + 1].parse().ok(),
-                    "depth" => depth = tokens[i + 1].parse().ok(),
+                    "wtime" => wtime = parse_value(&tokens, &mut i),
+                    "btime" => btime = parse_value(&tokens, &mut i),
+                    "movetime" => movetime = parse_value(&tokens, &mut i),
+                    "winc" => winc = parse_value(&tokens, &mut i),
+                    "binc" => binc = parse_value(&tokens, &mut i),
+                    "depth" => depth = parse_value(&tokens, &mut i),
+                    "nodes" => nodes = parse_value(&tokens, &mut i),
+                    "mate" => mate = parse_value(&tokens, &mut i),
+                    "movestogo" => movestogo = parse_value(&tokens, &mut i),
+                    "infinite" => infinite = true,
+                    "ponder" => ponder = true,
+                    "searchmoves" => {
+                        while i + 1 < tokens.len() && !GO_KEYWORDS.contains(&tokens[i + 1]) {
+                            searchmoves.push(tokens[i + 1].to_string());
+                            i += 1;
+                        }
+                    }
                     _ => {}
                 }
-                i += 2;
+                i += 1;
             }
 
             UciCommand::Go {
@@ -94,6 +148,12 @@ pub fn parse_command(input: &str) -> UciCommand {
                 winc,
                 binc,
                 depth,
+                nodes,
+                mate,
+                movestogo,
+                infinite,
+                ponder,
+                searchmoves,
             }
         }
         "setoption" => {
@@ -132,11 +192,28 @@ pub fn parse_command(input: &str) -> UciCommand {
         "perft" => {
             if tokens.len() >= 2 {
                 if let Ok(depth) = tokens[1].parse::<u32>() {
-                    return UciCommand::Perft { depth };
+                    return UciCommand::Perft { depth, divide: false };
                 }
             }
 
-            UciCommand::Perft { depth: 1 }
+            UciCommand::Perft { depth: 1, divide: false }
+        }
+        "perftsuite" => match tokens.get(1) {
+            Some(path) => UciCommand::PerftSuite {
+                path: path.to_string(),
+                max_depth: tokens.get(2).and_then(|d| d.parse().ok()),
+            },
+            None => UciCommand::Unknown,
+        },
+        "debug" => UciCommand::Debug(tokens.get(1) == Some(&"on")),
+        "bench" => {
+            let arg = |i: usize| tokens.get(i).and_then(|t| t.parse().ok());
+
+            UciCommand::Bench {
+                depth: arg(1),
+                hash: arg(2),
+                threads: arg(3),
+            }
         }
 
         _ => UciCommand::Unknown,
@@ -151,3 +228,8 @@ pub fn uci_to_move(pos: &Chess, s: &str) -> Move {
     let uci: UciMove = s.parse().unwrap();
     uci.to_move(pos).unwrap()
 }
+
+pub fn try_uci_to_move(pos: &Chess, s: &str) -> Option<Move> {
+    let uci: UciMove = s.parse().ok()?;
+    uci.to_move(pos).ok()
+}
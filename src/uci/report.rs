@@ -0,0 +1,119 @@
+use std::time::Duration;
+use shakmaty::Move;
+
+use crate::engine::search::report::{IterationReport, SearchReporter};
+use crate::engine::tt::Bound;
+use crate::engine::types::Score;
+use crate::uci::parser::move_to_uci;
+
+// Root move updates are noise in short searches.
+const CURRMOVE_DELAY: Duration = Duration::from_millis(1000);
+
+pub struct UciReporter;
+
+impl SearchReporter for UciReporter {
+    fn iteration(&self, report: &IterationReport) {
+        let elapsed = report.elapsed.as_secs_f64();
+
+        let nps = if elapsed > 0.0 {
+            (report.nodes as f64 / elapsed) as u64
+        } else {
+            0
+        };
+
+        let bound = match report.bound {
+            Bound::Exact => "",
+            Bound::Lower => " lowerbound",
+            Bound::Upper => " upperbound",
+        };
+
+        for (i, (score, line)) in report.lines.iter().enumerate() {
+            println!(
+                "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} hashfull {} tbhits {} time {} pv{}",
+                report.depth,
+                report.seldepth,
+                i + 1,
+                score_to_uci(*score),
+                bound,
+                report.nodes,
+                nps,
+                report.hashfull,
+                report.tbhits,
+                report.elapsed.as_millis(),
+                pv_to_string(line)
+            );
+        }
+    }
+
+    fn current_move(&self, depth: usize, mv: &Move, number: usize, elapsed: Duration) {
+        if elapsed < CURRMOVE_DELAY {
+            return;
+        }
+
+        println!(
+            "info depth {} currmove {} currmovenumber {}",
+            depth,
+            move_to_uci(mv),
+            number
+        );
+    }
+}
+
+/// Formats a score as `cp <x>` or, for forced mates, `mate <moves>`.
+pub fn score_to_uci(score: Score) -> String {
+    match score.mate_moves() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score.0),
+    }
+}
+
+/// The `bestmove` answer, with a `ponder` move when there is one.
+pub fn bestmove_to_uci(best_move: Option<Move>, ponder_move: Option<Move>) -> String {
+    match (best_move, ponder_move) {
+        (Some(mv), Some(reply)) => format!("bestmove {} ponder {}", move_to_uci(&mv), move_to_uci(&reply)),
+        (Some(mv), None) => format!("bestmove {}", move_to_uci(&mv)),
+        (None, _) => "bestmove 0000".to_string(),
+    }
+}
+
+pub fn pv_to_string(line: &[Move]) -> String {
+    let mut s = String::new();
+
+    for mv in line {
+        s.push(' ');
+        s.push_str(move_to_uci(mv).as_str());
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use shakmaty::Chess;
+
+    use super::bestmove_to_uci;
+    use crate::engine::params::Params;
+    use crate::engine::search::context::SearchContext;
+    use crate::engine::search::limits::SearchLimits;
+    use crate::engine::search::search::{ponder_move, search};
+    use crate::engine::tt::TranspositionTable;
+
+    #[test]
+    fn prints_a_ponder_move_on_a_warm_table() {
+        let params = Params::default();
+        let tt = TranspositionTable::new(16);
+        let stop = AtomicBool::new(false);
+        let pos = Chess::default();
+
+        // The second search cuts off at the first reply, from the first one's entries
+        for _ in 0..2 {
+            let mut ctx = SearchContext::new(&params, 1, &tt, &stop);
+            tt.new_search();
+            search(&pos, &mut ctx, &SearchLimits::depth(5));
+
+            let line = bestmove_to_uci(ctx.pv.best_move(), ponder_move(&pos, &ctx));
+            assert!(line.contains(" ponder "), "{}", line);
+        }
+    }
+}
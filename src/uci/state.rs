@@ -1,14 +1,24 @@
 use shakmaty::Chess;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::engine::search::threads::SEARCH_STACK_SIZE;
 
 pub struct UciState {
     pub position: Chess,
     pub multipv: usize,
+    pub threads: usize,
+    /// Set by `debug on`: perft also checks the incremental hash at every node.
+    pub debug: bool,
     pub _wtime: u64,
     pub _btime: u64,
     pub _winc: u64,
     pub _binc: u64,
-    pub stop: AtomicBool,
+    pub stop: Arc<AtomicBool>,
+    /// Set while a `go ponder` search waits for `ponderhit`.
+    pub pondering: Arc<AtomicBool>,
+    pub search_thread: Option<JoinHandle<()>>,
 }
 
 impl UciState {
@@ -20,7 +30,40 @@ impl UciState {
             _winc: 0,
             _binc: 0,
             multipv: 1,
-            stop: AtomicBool::new(false),
+            threads: 1,
+            debug: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+        }
+    }
+
+    /// Spawns `job` on a fresh search thread, stopping any search still running.
+    pub fn start_search<F>(&mut self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.stop_search();
+        self.stop.store(false, Ordering::Relaxed);
+
+        let handle = thread::Builder::new()
+            .name("search".to_string())
+            .stack_size(SEARCH_STACK_SIZE)
+            .spawn(job)
+            .expect("failed to spawn search thread");
+
+        self.search_thread = Some(handle);
+    }
+
+    /// Signals the running search to stop and waits for it to print `bestmove`.
+    pub fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_search();
+    }
+
+    pub fn wait_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            let _ = handle.join();
         }
     }
 }